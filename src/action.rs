@@ -0,0 +1,250 @@
+use std::{fmt::Display, process::Command};
+
+use kinded::Kinded;
+
+use crate::{
+    browser::BrowserWrapper,
+    desktop::{Background, Desktop},
+    environment::Environment,
+    git::{self, GitIdentity},
+    process,
+    result::Result,
+};
+
+/// A single change to make to the desktop.
+#[derive(Debug, Kinded)]
+pub(crate) enum Action {
+    BrowserWrapper(BrowserWrapper),
+    Browser(Change),
+    Background(Change<Background>),
+    LockScreen(Change),
+    GtkTheme(Change),
+    IconTheme(Change),
+    MimeDefault { mime_type: String, change: Change },
+    NotificationBanners(Change<bool>),
+    AppNotifications { app: String, change: Change<bool> },
+    FavouriteApps(Change<List>),
+    EnabledExtensions(Change<List>),
+    DisabledExtensions(Change<List>),
+    GitIdentity(Change<GitIdentity>),
+    GitInclude,
+    Environment(Change<Environment>),
+}
+
+impl Action {
+    pub(crate) fn apply(&self, desktop: &dyn Desktop) -> Result<()> {
+        match self {
+            Self::BrowserWrapper(wrapper) => wrapper.install(),
+            Self::Browser(change) => process::run(&mut browser_command(&change.target)),
+            Self::Background(change) => desktop.set_background(&change.target),
+            Self::LockScreen(change) => desktop.set_lock_screen_background(&change.target),
+            Self::GtkTheme(change) => desktop.set_gtk_theme(&change.target),
+            Self::IconTheme(change) => desktop.set_icon_theme(&change.target),
+            Self::MimeDefault { mime_type, change } => {
+                process::run(&mut mime_default_command(mime_type, &change.target))
+            }
+            Self::NotificationBanners(change) => desktop.set_notification_banners(change.target),
+            Self::AppNotifications { app, change } => {
+                desktop.set_app_notifications(app, change.target)
+            }
+            Self::FavouriteApps(change) => desktop.set_favourite_apps(&change.target.0),
+            Self::EnabledExtensions(change) => desktop.set_enabled_extensions(&change.target.0),
+            Self::DisabledExtensions(change) => desktop.set_disabled_extensions(&change.target.0),
+            Self::GitIdentity(change) => change.target.apply(),
+            Self::GitInclude => git::install_include(),
+            Self::Environment(change) => change.target.apply(),
+        }
+    }
+
+    /// The action which would undo this one, if the previous value is known.
+    pub(crate) fn reverse(&self) -> Option<Self> {
+        Some(match self {
+            Self::BrowserWrapper(_) => return None,
+            Self::Browser(change) => Self::Browser(change.reverse()?),
+            Self::Background(change) => Self::Background(change.reverse()?),
+            Self::LockScreen(change) => Self::LockScreen(change.reverse()?),
+            Self::GtkTheme(change) => Self::GtkTheme(change.reverse()?),
+            Self::IconTheme(change) => Self::IconTheme(change.reverse()?),
+            Self::MimeDefault { mime_type, change } => Self::MimeDefault {
+                mime_type: mime_type.clone(),
+                change: change.reverse()?,
+            },
+            Self::NotificationBanners(change) => Self::NotificationBanners(change.reverse()?),
+            Self::AppNotifications { app, change } => Self::AppNotifications {
+                app: app.clone(),
+                change: change.reverse()?,
+            },
+            Self::FavouriteApps(change) => Self::FavouriteApps(change.reverse()?),
+            Self::EnabledExtensions(change) => Self::EnabledExtensions(change.reverse()?),
+            Self::DisabledExtensions(change) => Self::DisabledExtensions(change.reverse()?),
+            Self::GitIdentity(change) => Self::GitIdentity(change.reverse()?),
+            Self::GitInclude => return None,
+            Self::Environment(change) => Self::Environment(change.reverse()?),
+        })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self.kind() {
+            ActionKind::BrowserWrapper => "browser-wrapper",
+            ActionKind::Browser => "browser",
+            ActionKind::Background => "background",
+            ActionKind::LockScreen => "lock-screen",
+            ActionKind::GtkTheme => "gtk-theme",
+            ActionKind::IconTheme => "icon-theme",
+            ActionKind::MimeDefault => "default-app",
+            ActionKind::NotificationBanners => "notification-banners",
+            ActionKind::AppNotifications => "notifications",
+            ActionKind::FavouriteApps => "favourite-apps",
+            ActionKind::EnabledExtensions => "enabled-extensions",
+            ActionKind::DisabledExtensions => "disabled-extensions",
+            ActionKind::GitIdentity => "git-identity",
+            ActionKind::GitInclude => "git-include",
+            ActionKind::Environment => "environment",
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BrowserWrapper(wrapper) => {
+                write!(f, "{}: write {}", self.name(), wrapper.path().display())?;
+                for line in wrapper.contents().lines() {
+                    write!(f, "\n  {line}")?;
+                }
+            }
+            Self::Browser(change) => write!(
+                f,
+                "{}: {}\n  {}",
+                self.name(),
+                change,
+                CommandLine(&browser_command(&change.target))
+            )?,
+            Self::MimeDefault { mime_type, change } => write!(
+                f,
+                "{} for {}: {}\n  {}",
+                self.name(),
+                mime_type,
+                change,
+                CommandLine(&mime_default_command(mime_type, &change.target))
+            )?,
+            Self::AppNotifications { app, change } => {
+                write!(f, "{} for {}: {}", self.name(), app, change)?
+            }
+            Self::NotificationBanners(change) => write!(f, "{}: {}", self.name(), change)?,
+            Self::FavouriteApps(change)
+            | Self::EnabledExtensions(change)
+            | Self::DisabledExtensions(change) => write!(f, "{}: {}", self.name(), change)?,
+            Self::GitIdentity(change) => write!(f, "{}: {}", self.name(), change)?,
+            Self::GitInclude => write!(
+                f,
+                "{}: add {} to the global gitconfig",
+                self.name(),
+                git::identity_path().display()
+            )?,
+            Self::Environment(change) => write!(f, "{}: {}", self.name(), change)?,
+            Self::Background(change) => write!(f, "{}: {}", self.name(), change)?,
+            Self::LockScreen(change) | Self::GtkTheme(change) | Self::IconTheme(change) => {
+                write!(f, "{}: {}", self.name(), change)?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A setting's current value, and the value it is to be given.
+#[derive(Debug)]
+pub(crate) struct Change<T = String> {
+    current: Option<T>,
+    target: T,
+}
+
+impl<T: Clone> Change<T> {
+    pub(crate) fn new(current: Option<T>, target: impl Into<T>) -> Self {
+        Self {
+            current,
+            target: target.into(),
+        }
+    }
+
+    pub(crate) fn target(&self) -> &T {
+        &self.target
+    }
+
+    fn reverse(&self) -> Option<Self> {
+        let current = self.current.clone()?;
+        Some(Self::new(Some(self.target.clone()), current))
+    }
+}
+
+impl<T: Display + PartialEq> Display for Change<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.current {
+            Some(current) if *current == self.target => write!(f, "{current} (unchanged)"),
+            Some(current) => write!(f, "{current} -> {}", self.target),
+            None => write!(f, "unknown -> {}", self.target),
+        }
+    }
+}
+
+/// A list of values, such as the desktop IDs of favourite apps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct List(pub(crate) Vec<String>);
+
+impl From<Vec<String>> for List {
+    fn from(value: Vec<String>) -> Self {
+        Self(value)
+    }
+}
+
+impl Display for List {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}]", self.0.join(", "))
+    }
+}
+
+fn browser_command(browser: &str) -> Command {
+    let mut command = Command::new("xdg-settings");
+    command.args(["set", "default-web-browser", browser]);
+    command
+}
+
+fn mime_default_command(mime_type: &str, app: &str) -> Command {
+    let mut command = Command::new("xdg-mime");
+    command.args(["default", app, mime_type]);
+    command
+}
+
+/// Formats a command as it would be typed into a shell.
+struct CommandLine<'a>(&'a Command);
+
+impl Display for CommandLine<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.get_program().to_string_lossy())?;
+        for arg in self.0.get_args() {
+            write!(f, " {}", arg.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reverse_background() {
+        let original = Background {
+            light: "file:///light.png".into(),
+            dark: "file:///dark.png".into(),
+        };
+        let action = Action::Background(Change::new(
+            Some(original.clone()),
+            "file:///work.png".to_string(),
+        ));
+        let Some(Action::Background(reverse)) = action.reverse() else {
+            panic!("expected a background to revert to");
+        };
+        assert_eq!(&original, reverse.target());
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    fs,
+    path::Path,
+    process::{self as std_process, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use gio::{glib::SpawnFlags, prelude::AppInfoExt};
+use serde::{Deserialize as Deserialise, Serialize as Serialise};
+
+use crate::{error::Error, process, result::Result};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An application to start when a profile is entered and close when it is left.
+#[derive(Clone, Debug, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum App {
+    /// An application installed with a `.desktop` file, such as `slack.desktop`.
+    Desktop(String),
+
+    /// A shell command.
+    Command(String),
+}
+
+impl App {
+    /// Start this application unless it is already running, returning the process started.
+    pub(crate) fn launch(&self) -> Result<Option<LaunchedApp>> {
+        if self.is_running()? {
+            return Ok(None);
+        }
+
+        let pid = match self {
+            Self::Desktop(id) => {
+                let mut pid = None;
+                self.app_info(id)?.launch_uris_as_manager(
+                    &[],
+                    None::<&gio::AppLaunchContext>,
+                    SpawnFlags::SEARCH_PATH,
+                    None,
+                    Some(&mut |_: &gio::DesktopAppInfo, launched: gio::glib::Pid| {
+                        pid = Some(launched.0 as u32)
+                    }),
+                )?;
+                pid
+            }
+            Self::Command(command) => Some(
+                Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?
+                    .id(),
+            ),
+        };
+        Ok(pid.and_then(|pid| LaunchedApp::new(self.clone(), pid)))
+    }
+
+    fn app_info(&self, id: &str) -> Result<gio::DesktopAppInfo> {
+        gio::DesktopAppInfo::new(id).ok_or_else(|| Error::UnknownAppError { id: id.into() })
+    }
+
+    /// The arguments this application runs with.
+    fn command_line(&self) -> Result<Vec<String>> {
+        let command_line = match self {
+            Self::Desktop(id) => self
+                .app_info(id)?
+                .commandline()
+                .map(|command_line| command_line.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            Self::Command(command) => command.clone(),
+        };
+        Ok(split_command_line(&command_line))
+    }
+
+    /// Whether a process with the same command line as this application is running, whoever
+    /// started it.
+    fn is_running(&self) -> Result<bool> {
+        let command_line = self.command_line()?;
+        let own_pid = std_process::id();
+        Ok(pids()?.filter(|pid| *pid != own_pid).any(|pid| {
+            let Ok(cmdline) = fs::read(format!("/proc/{pid}/cmdline")) else {
+                return false;
+            };
+            let args: Vec<_> = cmdline
+                .split(|b| *b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).to_string())
+                .collect();
+            runs(&args, &command_line)
+        }))
+    }
+}
+
+impl Display for App {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Desktop(id) => write!(f, "{id}"),
+            Self::Command(command) => write!(f, "{command}"),
+        }
+    }
+}
+
+/// A process which `commute` started for an application, and so may close.
+#[derive(Clone, Debug, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct LaunchedApp {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    app: App,
+    pid: u32,
+
+    /// When the process started, to tell it apart from a later process given the same PID.
+    start_time: u64,
+}
+
+impl LaunchedApp {
+    fn new(app: App, pid: u32) -> Option<Self> {
+        Some(Self {
+            app,
+            pid,
+            start_time: stat(pid)?.start_time,
+        })
+    }
+
+    pub(crate) fn app(&self) -> &App {
+        &self.app
+    }
+
+    /// Whether the process which was started is still running.
+    pub(crate) fn is_running(&self) -> bool {
+        stat(self.pid).is_some_and(|stat| stat.start_time == self.start_time && stat.is_live())
+    }
+
+    /// Ask the process and its children to close, killing them if they have not done so within
+    /// the timeout.
+    pub(crate) fn close(&self, timeout: Duration) -> Result<()> {
+        if !self.is_running() {
+            return Ok(());
+        }
+        let pids = descendants(self.pid, &parents()?);
+
+        signal("TERM", &pids)?;
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if !pids.iter().any(|pid| is_live(*pid)) {
+                return Ok(());
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        let remaining: Vec<_> = pids.into_iter().filter(|pid| is_live(*pid)).collect();
+        signal("KILL", &remaining)
+    }
+}
+
+/// Split a command line into arguments, leaving out the field codes of a `.desktop` file, such as
+/// `%U`, and the `@@u` and `@@` markers which Flatpak puts around them.
+fn split_command_line(command_line: &str) -> Vec<String> {
+    command_line
+        .split_whitespace()
+        .filter(|arg| !arg.starts_with('%') && !arg.starts_with("@@"))
+        .map(String::from)
+        .collect()
+}
+
+/// Whether a process's arguments are those of a command line, allowing the program to be given
+/// by its full path.
+fn runs(args: &[String], command_line: &[String]) -> bool {
+    let (Some((program, args)), Some((expected_program, expected_args))) =
+        (args.split_first(), command_line.split_first())
+    else {
+        return false;
+    };
+    let program_matches = if expected_program.contains('/') {
+        program == expected_program
+    } else {
+        Path::new(program).file_name() == Some(expected_program.as_ref())
+    };
+    program_matches && args == expected_args
+}
+
+/// The details of a process from `/proc/<pid>/stat`.
+struct Stat {
+    state: char,
+    ppid: u32,
+    start_time: u64,
+}
+
+impl Stat {
+    fn parse(stat: &str) -> Option<Self> {
+        // The command name is in brackets and may contain spaces or brackets itself.
+        let (_, fields) = stat.rsplit_once(')')?;
+        let fields: Vec<_> = fields.split_whitespace().collect();
+        Some(Self {
+            state: fields.first()?.chars().next()?,
+            ppid: fields.get(1)?.parse().ok()?,
+            start_time: fields.get(19)?.parse().ok()?,
+        })
+    }
+
+    /// Whether the process has not yet exited, as zombies await only their parent.
+    fn is_live(&self) -> bool {
+        !matches!(self.state, 'Z' | 'X')
+    }
+}
+
+fn stat(pid: u32) -> Option<Stat> {
+    Stat::parse(&fs::read_to_string(format!("/proc/{pid}/stat")).ok()?)
+}
+
+fn is_live(pid: u32) -> bool {
+    stat(pid).is_some_and(|stat| stat.is_live())
+}
+
+fn pids() -> Result<impl Iterator<Item = u32>> {
+    Ok(fs::read_dir("/proc")?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok()))
+}
+
+/// Each running process with its parent.
+fn parents() -> Result<Vec<(u32, u32)>> {
+    Ok(pids()?
+        .filter_map(|pid| Some((pid, stat(pid)?.ppid)))
+        .collect())
+}
+
+/// A process and all processes descended from it, parents first.
+fn descendants(pid: u32, parents: &[(u32, u32)]) -> Vec<u32> {
+    let mut found = vec![pid];
+    let mut seen = BTreeSet::from([pid]);
+    let mut index = 0;
+    while let Some(parent) = found.get(index).copied() {
+        for (child, _) in parents.iter().filter(|(_, ppid)| *ppid == parent) {
+            if seen.insert(*child) {
+                found.push(*child);
+            }
+        }
+        index += 1;
+    }
+    found
+}
+
+/// Send a signal to each process, ignoring those which have exited since they were found.
+fn signal(signal: &str, pids: &[u32]) -> Result<()> {
+    for pid in pids {
+        match process::run(
+            Command::new("kill")
+                .arg(format!("-{signal}"))
+                .arg(pid.to_string()),
+        ) {
+            Err(_) if !is_live(*pid) => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(command_line: &str) -> Vec<String> {
+        command_line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn split_command_line() {
+        assert_eq!(
+            args("/usr/bin/flatpak run --branch=stable com.slack.Slack"),
+            super::split_command_line(
+                "/usr/bin/flatpak run --branch=stable com.slack.Slack @@u %U @@"
+            )
+        );
+    }
+
+    #[test]
+    fn runs() {
+        let command_line = args("flatpak run com.slack.Slack");
+        assert!(super::runs(
+            &args("/usr/bin/flatpak run com.slack.Slack"),
+            &command_line
+        ));
+        assert!(!super::runs(
+            &args("/usr/bin/flatpak run org.signal.Signal"),
+            &command_line
+        ));
+        assert!(!super::runs(&args("flatpak"), &command_line));
+        assert!(!super::runs(
+            &args("/opt/flatpak run com.slack.Slack"),
+            &args("/usr/bin/flatpak run com.slack.Slack")
+        ));
+    }
+
+    #[test]
+    fn parse_stat() {
+        let stat = Stat::parse(
+            "4321 (Web Content (2)) S 1234 4321 4321 0 -1 4194560 1 0 0 0 0 0 0 0 20 0 1 0 98765 0 0",
+        )
+        .unwrap();
+        assert_eq!('S', stat.state);
+        assert_eq!(1234, stat.ppid);
+        assert_eq!(98765, stat.start_time);
+    }
+
+    #[test]
+    fn descendants() {
+        let parents = [(2, 1), (3, 2), (4, 1), (5, 3), (6, 4)];
+        assert_eq!(vec![2, 3, 5], super::descendants(2, &parents));
+    }
+
+    #[test]
+    fn launch_and_close() {
+        let app = App::Command("sleep 61.25".into());
+        let launched = app.launch().unwrap().expect("expected the app to start");
+        assert!(launched.is_running());
+
+        // Wait for the shell to start the command.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !app.is_running().unwrap() && Instant::now() < deadline {
+            thread::sleep(POLL_INTERVAL);
+        }
+        assert!(app.launch().unwrap().is_none());
+
+        launched.close(Duration::from_secs(5)).unwrap();
+        assert!(!launched.is_running());
+        assert!(!app.is_running().unwrap());
+
+        // The process has already gone.
+        launched.close(Duration::from_secs(5)).unwrap();
+    }
+}
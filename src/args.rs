@@ -1,5 +1,7 @@
-use chrono::Duration;
-use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDate};
+use clap::{ArgAction, Args as ClapArgs, Parser, Subcommand, ValueEnum};
 use kinded::Kinded;
 
 #[derive(Parser, Debug)]
@@ -8,12 +10,65 @@ use kinded::Kinded;
 pub(crate) struct Args {
     #[command(subcommand)]
     command: Option<Command>,
+
+    /// Print planned changes without applying them
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// If applying a profile fails, restore the settings it had already changed
+    #[arg(long, global = true, conflicts_with = "best_effort")]
+    rollback_on_error: bool,
+
+    /// Apply every part of a profile even if an earlier part fails
+    #[arg(long, global = true)]
+    best_effort: bool,
+
+    /// The config file to use instead of the default, also settable with COMMUTE_CONFIG
+    #[arg(long = "config", value_name = "PATH", global = true)]
+    config_path: Option<PathBuf>,
+
+    /// A note on why the profile is being changed, kept in the history
+    #[arg(long = "reason", value_name = "NOTE", global = true)]
+    note: Option<String>,
+
+    /// Log more detail to stderr, repeat for more
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Log less to stderr, repeat to log nothing
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    quiet: u8,
 }
 
 impl Args {
     pub(crate) fn command(&self) -> Option<&Command> {
         self.command.as_ref()
     }
+
+    pub(crate) fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub(crate) fn rollback_on_error(&self) -> bool {
+        self.rollback_on_error
+    }
+
+    pub(crate) fn best_effort(&self) -> bool {
+        self.best_effort
+    }
+
+    pub(crate) fn config_path(&self) -> Option<&Path> {
+        self.config_path.as_deref()
+    }
+
+    pub(crate) fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    /// How much to log to stderr, relative to the default of warnings and errors.
+    pub(crate) fn verbosity(&self) -> i8 {
+        self.verbose.min(i8::MAX as u8) as i8 - self.quiet.min(i8::MAX as u8) as i8
+    }
 }
 
 #[derive(Subcommand, Kinded, Debug, PartialEq, Eq, Default)]
@@ -36,17 +91,44 @@ pub(crate) enum Command {
     Work {
         #[clap(flatten)]
         input_duration: InputDuration,
+
+        /// Override even if the work-time cap has been reached
+        #[arg(long)]
+        force: bool,
     },
 
     /// Change configuration
     Config(ConfigCmd),
+
+    /// List installed browsers
+    Browsers,
+
+    /// Show the active profile and git identity
+    Status,
+
+    /// Print shell commands which set the active profile's environment variables
+    Env,
+
+    /// Show the time spent in each profile per day
+    Report(ReportArgs),
+
+    /// Compare the time spent in the work profile with the work hours
+    Overtime(OvertimeArgs),
+
+    /// Share the work schedule
+    Schedule(ScheduleCmd),
+
+    /// Show recent entries from the log file
+    Log(LogArgs),
 }
 
 #[cfg(test)]
 impl Command {
     fn input_duration(&self) -> Option<&InputDuration> {
         match self {
-            Self::Work { input_duration } | Self::Home { input_duration } => Some(input_duration),
+            Self::Work { input_duration, .. } | Self::Home { input_duration } => {
+                Some(input_duration)
+            }
             _ => None,
         }
     }
@@ -121,6 +203,76 @@ pub(crate) enum InputDurationUnit {
     Years,
 }
 
+#[derive(ClapArgs, Clone, Debug, PartialEq, Eq)]
+#[warn(missing_docs)]
+pub(crate) struct ReportArgs {
+    /// Report on this week so far (the default)
+    #[arg(long, conflicts_with_all = ["month", "from"])]
+    pub(crate) week: bool,
+
+    /// Report on this month so far
+    #[arg(long, conflicts_with = "from")]
+    pub(crate) month: bool,
+
+    /// The first day to report on, as yyyy-mm-dd
+    #[arg(long, requires = "to")]
+    pub(crate) from: Option<NaiveDate>,
+
+    /// The last day to report on, as yyyy-mm-dd
+    #[arg(long, requires = "from")]
+    pub(crate) to: Option<NaiveDate>,
+
+    #[arg(long, value_enum, default_value_t)]
+    pub(crate) format: ReportFormat,
+}
+
+#[derive(ClapArgs, Clone, Debug, PartialEq, Eq)]
+#[warn(missing_docs)]
+pub(crate) struct OvertimeArgs {
+    /// The number of weeks to show
+    #[arg(long, default_value_t = 4)]
+    pub(crate) weeks: usize,
+
+    /// Count the flexi-time balance from this day, as yyyy-mm-dd
+    #[arg(long, value_name = "DATE")]
+    pub(crate) reset: Option<NaiveDate>,
+}
+
+#[derive(ClapArgs, Clone, Debug, PartialEq, Eq)]
+#[warn(missing_docs)]
+pub(crate) struct LogArgs {
+    /// The number of entries to show
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub(crate) lines: usize,
+
+    /// Keep showing entries as they are written
+    #[arg(short, long)]
+    pub(crate) follow: bool,
+}
+
+#[derive(ClapArgs, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ScheduleCmd {
+    #[command(subcommand)]
+    pub(crate) schedule: Schedule,
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+#[warn(missing_docs)]
+pub(crate) enum Schedule {
+    /// Print the work schedule, with leave days and overrides, as iCalendar for import into a
+    /// calendar
+    Export,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[warn(missing_docs)]
+pub(crate) enum ReportFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+}
+
 #[derive(ClapArgs, Clone, Debug, PartialEq, Eq)]
 pub(crate) struct ConfigCmd {
     #[command(subcommand)]
@@ -138,6 +290,12 @@ pub(crate) enum Config {
 
     /// Interact with work hours
     WorkHours(WorkHoursConfig),
+
+    /// Interact with the desktop environment to configure
+    Desktop(DesktopConfig),
+
+    /// Print the paths of the config and state files in use
+    Path,
 }
 
 #[derive(ClapArgs, Clone, Debug, PartialEq, Eq)]
@@ -156,6 +314,7 @@ pub(crate) struct ProfileConfig {
 #[warn(missing_docs)]
 pub(crate) enum ConfigKey {
     Browser,
+    BrowserProfile,
     BackgroundDir,
     GtkTheme,
     IconTheme,
@@ -168,6 +327,13 @@ pub(crate) struct WorkHoursConfig {
     pub(crate) time: Option<String>,
 }
 
+#[derive(ClapArgs, Clone, Debug, PartialEq, Eq)]
+#[warn(missing_docs)]
+pub(crate) struct DesktopConfig {
+    /// If present, set the desktop to gnome, kde, xfce, wlroots or auto, otherwise print it
+    pub(crate) desktop: Option<String>,
+}
+
 #[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
 #[warn(missing_docs)]
 pub(crate) enum WorkHoursTransition {
@@ -196,6 +362,14 @@ mod test {
         );
     }
 
+    #[test]
+    fn dry_run() {
+        assert!(!Args::parse_from(["commute"]).dry_run());
+        assert!(Args::parse_from(["commute", "--dry-run"]).dry_run());
+        assert!(Args::parse_from(["commute", "--dry-run", "work"]).dry_run());
+        assert!(Args::parse_from(["commute", "home", "--dry-run"]).dry_run());
+    }
+
     #[test]
     fn norm() {
         assert_eq!(
@@ -260,6 +434,109 @@ mod test {
         }
     }
 
+    #[test]
+    fn status() {
+        assert_eq!(
+            CommandKind::Status,
+            Args::parse_from(["commute", "status"])
+                .command()
+                .expect("expected command")
+                .kind()
+        );
+    }
+
+    #[test]
+    fn env() {
+        assert_eq!(
+            CommandKind::Env,
+            Args::parse_from(["commute", "env"])
+                .command()
+                .expect("expected command")
+                .kind()
+        );
+    }
+
+    #[test]
+    fn report() {
+        let Some(Command::Report(report)) = Args::parse_from([
+            "commute", "report", "--from", "2023-08-01", "--to", "2023-08-31", "--format", "csv",
+        ])
+        .command
+        else {
+            panic!("expected report command");
+        };
+        assert_eq!(NaiveDate::from_ymd_opt(2023, 8, 1), report.from);
+        assert_eq!(ReportFormat::Csv, report.format);
+
+        Args::try_parse_from(["commute", "report", "--week", "--month"])
+            .expect_err("expected week and month to conflict");
+        Args::try_parse_from(["commute", "report", "--from", "2023-08-01"])
+            .expect_err("expected from to require to");
+    }
+
+    #[test]
+    fn overtime() {
+        let Some(Command::Overtime(overtime)) =
+            Args::parse_from(["commute", "overtime", "--reset", "2023-08-01"]).command
+        else {
+            panic!("expected overtime command");
+        };
+        assert_eq!(4, overtime.weeks);
+        assert_eq!(NaiveDate::from_ymd_opt(2023, 8, 1), overtime.reset);
+    }
+
+    #[test]
+    fn schedule_export() {
+        let Some(Command::Schedule(schedule)) =
+            Args::parse_from(["commute", "schedule", "export"]).command
+        else {
+            panic!("expected schedule command");
+        };
+        assert_eq!(Schedule::Export, schedule.schedule);
+    }
+
+    #[test]
+    fn rollback_on_error() {
+        let args = Args::parse_from(["commute", "--rollback-on-error", "work"]);
+        assert!(args.rollback_on_error());
+        assert!(Args::try_parse_from(["commute", "--rollback-on-error", "--best-effort"]).is_err());
+    }
+
+    #[test]
+    fn verbosity() {
+        assert_eq!(0, Args::parse_from(["commute"]).verbosity());
+        assert_eq!(2, Args::parse_from(["commute", "-vv", "work"]).verbosity());
+        assert_eq!(-1, Args::parse_from(["commute", "log", "-q"]).verbosity());
+        Args::try_parse_from(["commute", "-v", "-q"])
+            .expect_err("expected verbose and quiet to conflict");
+    }
+
+    #[test]
+    fn config_path() {
+        let args = Args::parse_from(["commute", "status", "--config", "/tmp/commute.yml"]);
+        assert_eq!(Some(Path::new("/tmp/commute.yml")), args.config_path());
+        assert_eq!(
+            Some(Config::Path),
+            Args::parse_from(["commute", "config", "path"])
+                .command()
+                .expect("expected command")
+                .config()
+                .expect("expected config")
+                .config
+        );
+    }
+
+    #[test]
+    fn browsers() {
+        assert_eq!(
+            CommandKind::Browsers,
+            Args::parse_from(["commute", "browsers"])
+                .command()
+                .expect("expected command")
+                .kind()
+        );
+    }
+
     #[test]
     fn config() {
         assert_eq!(
@@ -305,6 +582,7 @@ mod test {
 
         let keys = [
             (ConfigKey::Browser, "browser"),
+            (ConfigKey::BrowserProfile, "browser-profile"),
             (ConfigKey::BackgroundDir, "background-dir"),
             (ConfigKey::GtkTheme, "gtk-theme"),
             (ConfigKey::IconTheme, "icon-theme"),
@@ -0,0 +1,391 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use directories::BaseDirs;
+
+use crate::{error::Error, result::Result, xdg};
+
+const HTTP_HANDLER: &str = "x-scheme-handler/http";
+const MAX_SUGGESTIONS: usize = 3;
+const MIN_SIMILARITY: f64 = 0.7;
+
+/// The desktop IDs of all installed applications which can open web pages.
+pub(crate) fn installed_browsers() -> Result<Vec<String>> {
+    let mut browsers = vec![];
+    for dir in xdg::data_dirs() {
+        let applications = dir.join("applications");
+        if applications.is_dir() {
+            find_browsers(&applications, &applications, &mut browsers);
+        }
+    }
+    browsers.sort();
+    browsers.dedup();
+    Ok(browsers)
+}
+
+/// Installed browsers with names similar to the given one, most similar first.
+pub(crate) fn similar_browsers(browser: &str, installed: &[String]) -> Vec<String> {
+    let browser = browser.trim_end_matches(".desktop").to_lowercase();
+    let mut scored: Vec<_> = installed
+        .iter()
+        .map(|candidate| {
+            let name = candidate.trim_end_matches(".desktop").to_lowercase();
+            let score = if name == browser {
+                1.0
+            } else if name.contains(&browser) || browser.contains(&name) {
+                0.95
+            } else {
+                strsim::jaro_winkler(&browser, &name)
+            };
+            (score, candidate)
+        })
+        .filter(|(score, _)| *score >= MIN_SIMILARITY)
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// Add the browsers among the applications in a directory, skipping any which cannot be read.
+fn find_browsers(root: &Path, dir: &Path, browsers: &mut Vec<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::debug!(dir:% = dir.display(); "skipping unreadable applications: {e}");
+            return;
+        }
+    };
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                log::debug!(dir:% = dir.display(); "skipping unreadable application: {e}");
+                continue;
+            }
+        };
+        if path.is_dir() {
+            find_browsers(root, &path, browsers);
+            continue;
+        }
+        if path.extension().and_then(|e| e.to_str()) != Some("desktop") {
+            continue;
+        }
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if !handles_http(&contents) {
+            continue;
+        }
+
+        // Desktop IDs of files in subdirectories join the path components with dashes.
+        let id = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("-");
+        if !id.starts_with("commute-") {
+            browsers.push(id);
+        }
+    }
+}
+
+fn handles_http(desktop_file: &str) -> bool {
+    let mut handles_http = false;
+    for line in desktop_file.lines() {
+        if line.starts_with('[') && line != "[Desktop Entry]" {
+            break;
+        }
+        if line == "Hidden=true" {
+            return false;
+        }
+        if let Some(mime_types) = line.strip_prefix("MimeType=") {
+            handles_http = mime_types.split(';').any(|m| m == HTTP_HANDLER);
+        }
+    }
+    handles_http
+}
+
+/// A `.desktop` file which launches a browser with a particular browser profile.
+#[derive(Debug)]
+pub(crate) struct BrowserWrapper {
+    id: String,
+    path: PathBuf,
+    contents: String,
+}
+
+impl BrowserWrapper {
+    /// Derive a wrapper from the installed `.desktop` file of the given browser.
+    pub(crate) fn new(browser: &str, browser_profile: &str) -> Result<Self> {
+        let app_info = gio::DesktopAppInfo::new(browser)
+            .ok_or_else(|| Error::UnknownAppError { id: browser.into() })?;
+        let source_path = app_info
+            .filename()
+            .ok_or_else(|| Error::UnknownAppError { id: browser.into() })?;
+        let source = fs::read_to_string(&source_path)?;
+
+        let family = BrowserFamily::detect(browser, &source).ok_or_else(|| {
+            Error::UnsupportedBrowserError {
+                browser: browser.into(),
+            }
+        })?;
+
+        let id = format!(
+            "commute-{}-{}.desktop",
+            browser.trim_end_matches(".desktop"),
+            slug(browser_profile)
+        );
+        let path = BaseDirs::new()
+            .ok_or_else(|| anyhow::anyhow!("cannot find home directory"))?
+            .data_dir()
+            .join("applications")
+            .join(&id);
+        let contents = wrap(
+            &source,
+            browser_profile,
+            &family.profile_args(browser_profile),
+        );
+        Ok(Self { id, path, contents })
+    }
+
+    /// The desktop ID to register as the default browser.
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub(crate) fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    pub(crate) fn install(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(fs::write(&self.path, &self.contents)?)
+    }
+}
+
+/// Browsers which share a way of selecting a browser profile from the command line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum BrowserFamily {
+    Firefox,
+    Chromium,
+}
+
+impl BrowserFamily {
+    fn detect(browser: &str, desktop_file: &str) -> Option<Self> {
+        let exec = desktop_file
+            .lines()
+            .find_map(|line| line.strip_prefix("Exec="))
+            .unwrap_or_default();
+        let haystack = format!("{browser} {exec}").to_lowercase();
+        if ["firefox", "librewolf", "waterfox", "floorp"]
+            .iter()
+            .any(|name| haystack.contains(name))
+        {
+            Some(Self::Firefox)
+        } else if ["chrom", "brave", "vivaldi", "edge", "opera"]
+            .iter()
+            .any(|name| haystack.contains(name))
+        {
+            Some(Self::Chromium)
+        } else {
+            None
+        }
+    }
+
+    fn profile_args(&self, browser_profile: &str) -> String {
+        match self {
+            Self::Firefox => format!("-P {}", quote(browser_profile)),
+            Self::Chromium => format!("--profile-directory={}", quote(browser_profile)),
+        }
+    }
+}
+
+/// Rewrite a browser's `.desktop` file so that every command it runs uses the given profile.
+fn wrap(source: &str, browser_profile: &str, profile_args: &str) -> String {
+    let mut in_main_group = false;
+    let mut lines = vec![];
+    for line in source.lines() {
+        if line.starts_with('[') {
+            in_main_group = line == "[Desktop Entry]";
+        }
+
+        if let Some(exec) = line.strip_prefix("Exec=") {
+            lines.push(format!("Exec={}", insert_args(exec, profile_args)));
+        } else if let (true, Some(name)) = (in_main_group, line.strip_prefix("Name=")) {
+            lines.push(format!("Name={name} ({browser_profile})"));
+        } else if in_main_group && line.starts_with("NoDisplay=") {
+            continue;
+        } else {
+            lines.push(line.to_string());
+        }
+
+        if line == "[Desktop Entry]" {
+            lines.push("NoDisplay=true".into());
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Insert arguments before the first field code, such as `%u`, so they reach the browser even
+/// when it is run through a launcher such as `flatpak run`.
+fn insert_args(exec: &str, args: &str) -> String {
+    let mut words: Vec<&str> = exec.split(' ').collect();
+    let index = words
+        .iter()
+        .position(|word| word.starts_with('%') || word.starts_with("@@"))
+        .unwrap_or(words.len());
+    words.insert(index, args);
+    words.join(" ")
+}
+
+fn quote(arg: &str) -> String {
+    if !arg.contains(|c: char| c.is_whitespace() || "\"`$\\'".contains(c)) {
+        return arg.into();
+    }
+    let escaped: String = arg
+        .chars()
+        .flat_map(|c| match c {
+            '"' | '`' | '$' | '\\' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect();
+    format!("\"{escaped}\"")
+}
+
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIREFOX: &str = "[Desktop Entry]
+Name=Firefox
+Exec=/usr/lib/firefox/firefox %u
+Actions=new-private-window;
+
+[Desktop Action new-private-window]
+Name=New Private Window
+Exec=/usr/lib/firefox/firefox --private-window %u
+";
+
+    #[test]
+    fn detect() {
+        assert_eq!(
+            Some(BrowserFamily::Firefox),
+            BrowserFamily::detect("firefox.desktop", FIREFOX)
+        );
+        assert_eq!(
+            Some(BrowserFamily::Chromium),
+            BrowserFamily::detect(
+                "com.brave.Browser.desktop",
+                "[Desktop Entry]\nExec=/usr/bin/flatpak run com.brave.Browser @@u %U @@\n"
+            )
+        );
+        assert_eq!(
+            None,
+            BrowserFamily::detect("lynx.desktop", "[Desktop Entry]\nExec=lynx %u\n")
+        );
+    }
+
+    #[test]
+    fn wrap_firefox() {
+        assert_eq!(
+            "[Desktop Entry]
+NoDisplay=true
+Name=Firefox (Work)
+Exec=/usr/lib/firefox/firefox -P Work %u
+Actions=new-private-window;
+
+[Desktop Action new-private-window]
+Name=New Private Window
+Exec=/usr/lib/firefox/firefox --private-window -P Work %u
+",
+            wrap(
+                FIREFOX,
+                "Work",
+                &BrowserFamily::Firefox.profile_args("Work")
+            )
+        );
+    }
+
+    #[test]
+    fn handles_http() {
+        assert!(super::handles_http(
+            "[Desktop Entry]\nMimeType=text/html;x-scheme-handler/http;x-scheme-handler/https;\n"
+        ));
+        assert!(!super::handles_http(
+            "[Desktop Entry]\nMimeType=text/html;x-scheme-handler/http;\nHidden=true\n"
+        ));
+        assert!(!super::handles_http(
+            "[Desktop Entry]\nMimeType=x-scheme-handler/https;\n"
+        ));
+    }
+
+    #[test]
+    fn similar_browsers() {
+        let installed = [
+            "chromium.desktop".to_string(),
+            "firefox.desktop".to_string(),
+            "org.gnome.Epiphany.desktop".to_string(),
+        ];
+        assert_eq!(
+            vec!["firefox.desktop"],
+            super::similar_browsers("firefox", &installed)
+        );
+        assert_eq!(
+            vec!["firefox.desktop"],
+            super::similar_browsers("firefx.desktop", &installed)
+        );
+        assert!(super::similar_browsers("lynx", &installed).is_empty());
+    }
+
+    #[test]
+    fn find_browsers() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("kde4")).unwrap();
+        let browser = "[Desktop Entry]\nMimeType=text/html;x-scheme-handler/http;\n";
+        fs::write(root.join("kde4").join("konqueror.desktop"), browser).unwrap();
+        fs::write(root.join("commute-firefox-work.desktop"), browser).unwrap();
+        fs::write(root.join("firefox.desktop"), FIREFOX).unwrap();
+
+        let mut browsers = vec![];
+        super::find_browsers(root, &root.join("missing"), &mut browsers);
+        super::find_browsers(root, root, &mut browsers);
+        assert_eq!(vec!["kde4-konqueror.desktop"], browsers);
+    }
+
+    #[test]
+    fn insert_args() {
+        assert_eq!(
+            "/usr/bin/flatpak run org.chromium.Chromium --profile-directory=Default @@u %U @@",
+            super::insert_args(
+                "/usr/bin/flatpak run org.chromium.Chromium @@u %U @@",
+                "--profile-directory=Default"
+            )
+        );
+        assert_eq!("chromium -P x", super::insert_args("chromium", "-P x"));
+    }
+}
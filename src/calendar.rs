@@ -0,0 +1,437 @@
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc,
+};
+
+use crate::{report::start_of_day, schedule, settings::Settings};
+
+const SUMMARY: &str = "Working";
+const SERIES_UID: &str = concat!("work-hours@", env!("CARGO_PKG_NAME"));
+
+/// Export the work schedule from `now` onwards as an iCalendar file. The work hours are a weekly
+/// series of events, and leave days and overrides are exceptions to it.
+pub(crate) fn ics(settings: &Settings, now: &DateTime<Local>) -> String {
+    let mut calendar = Calendar::new(now);
+    let work_hours = settings.work_hours();
+    let today = now.date_naive();
+
+    let Some((first_start, first_end)) = today
+        .iter_days()
+        .take(7)
+        .find_map(|date| schedule::weekday_hours(work_hours, date))
+    else {
+        return calendar.finish();
+    };
+
+    // Exceptions can only fall on days up to the last leave day or the end of the override.
+    let exceptions_end = settings
+        .leave_days()
+        .iter()
+        .map(|date| *date + Duration::days(1))
+        .chain(
+            settings
+                .r#override()
+                .map(|r#override| r#override.until().date_naive() + Duration::days(1)),
+        )
+        .max()
+        .and_then(start_of_day)
+        .filter(|end| end > now);
+    let mut periods: Vec<_> = exceptions_end
+        .map(|end| schedule::work_periods(settings, now, &end))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|period| (period, false))
+        .collect();
+
+    let mut excluded = vec![];
+    let mut moved = vec![];
+    let days = exceptions_end.map_or(0, |end| (end.date_naive() - today).num_days());
+    for date in today.iter_days().take(days as usize) {
+        let Some((start, end)) = schedule::weekday_hours(work_hours, date) else {
+            continue;
+        };
+        if end <= *now {
+            continue;
+        }
+        let from = start.max(*now);
+        let overlapping = periods
+            .iter_mut()
+            .find(|((period_start, period_end), claimed)| {
+                !claimed && *period_start < end && from < *period_end
+            });
+        match overlapping {
+            None => excluded.push(start),
+            Some(((period_start, period_end), claimed)) => {
+                *claimed = true;
+                // Work already in force may have started at the scheduled time, before `now`.
+                let period_start = if *period_start <= from {
+                    start.min(*period_start)
+                } else {
+                    *period_start
+                };
+                if (period_start, *period_end) != (start, end) {
+                    moved.push((start, period_start, *period_end));
+                }
+            }
+        }
+    }
+
+    calendar.begin_event(SERIES_UID);
+    calendar.date_time("DTSTART", &first_start);
+    calendar.date_time("DTEND", &first_end);
+    calendar.line("RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR");
+    for start in &excluded {
+        calendar.date_time("EXDATE", start);
+    }
+    calendar.line("END:VEVENT");
+
+    for (occurrence, start, end) in &moved {
+        calendar.begin_event(SERIES_UID);
+        calendar.date_time("RECURRENCE-ID", occurrence);
+        calendar.date_time("DTSTART", start);
+        calendar.date_time("DTEND", end);
+        calendar.line("END:VEVENT");
+    }
+
+    for ((start, end), _) in periods.iter().filter(|(_, claimed)| !claimed) {
+        calendar.begin_event(&format!(
+            "work-{}@{}",
+            start.format("%Y%m%dT%H%M%S"),
+            env!("CARGO_PKG_NAME")
+        ));
+        calendar.date_time("DTSTART", start);
+        calendar.date_time("DTEND", end);
+        calendar.line("END:VEVENT");
+    }
+
+    calendar.finish()
+}
+
+/// An iCalendar file being written.
+struct Calendar {
+    src: String,
+    stamp: String,
+
+    /// The IANA name of the local time zone, or none to write floating times.
+    tzid: Option<String>,
+}
+
+impl Calendar {
+    fn new(now: &DateTime<Local>) -> Self {
+        let mut calendar = Self {
+            src: String::new(),
+            stamp: now.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string(),
+            tzid: iana_time_zone::get_timezone().ok(),
+        };
+        calendar.line("BEGIN:VCALENDAR");
+        calendar.line("VERSION:2.0");
+        calendar.line(concat!("PRODID:-//kcza//", env!("CARGO_PKG_NAME"), "//EN"));
+        calendar.line("CALSCALE:GREGORIAN");
+        if let Some(tzid) = calendar.tzid.clone() {
+            calendar.time_zone(&tzid, now.year(), local_offset);
+        }
+        calendar
+    }
+
+    /// Describe the time zone which times are given in, with its changes of offset in the given
+    /// year repeating yearly.
+    fn time_zone(&mut self, tzid: &str, year: i32, offset: impl Fn(&NaiveDateTime) -> i32) {
+        self.line("BEGIN:VTIMEZONE");
+        self.line(&format!("TZID:{tzid}"));
+        let observances = observances(year, &offset);
+        if observances.is_empty() {
+            let offset = NaiveDate::from_ymd_opt(year, 1, 1)
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map_or(0, |time| offset(&time));
+            self.line("BEGIN:STANDARD");
+            self.line("DTSTART:19700101T000000");
+            self.line(&format!("TZOFFSETFROM:{}", utc_offset(offset)));
+            self.line(&format!("TZOFFSETTO:{}", utc_offset(offset)));
+            self.line("END:STANDARD");
+        }
+        for observance in observances {
+            let kind = if observance.to > observance.from {
+                "DAYLIGHT"
+            } else {
+                "STANDARD"
+            };
+            self.line(&format!("BEGIN:{kind}"));
+            self.line(&format!(
+                "DTSTART:{}",
+                observance.onset.format("%Y%m%dT%H%M%S")
+            ));
+            self.line(&format!(
+                "RRULE:FREQ=YEARLY;BYMONTH={};BYDAY={}",
+                observance.onset.month(),
+                weekday_in_month(&observance.onset.date())
+            ));
+            self.line(&format!("TZOFFSETFROM:{}", utc_offset(observance.from)));
+            self.line(&format!("TZOFFSETTO:{}", utc_offset(observance.to)));
+            self.line(&format!("END:{kind}"));
+        }
+        self.line("END:VTIMEZONE");
+    }
+
+    fn begin_event(&mut self, uid: &str) {
+        self.line("BEGIN:VEVENT");
+        self.line(&format!("UID:{uid}"));
+        self.line(&format!("DTSTAMP:{}", self.stamp));
+        self.line(&format!("SUMMARY:{SUMMARY}"));
+    }
+
+    fn date_time(&mut self, name: &str, time: &DateTime<Local>) {
+        let time = time.format("%Y%m%dT%H%M%S");
+        let line = match &self.tzid {
+            Some(tzid) => format!("{name};TZID={tzid}:{time}"),
+            None => format!("{name}:{time}"),
+        };
+        self.line(&line);
+    }
+
+    fn line(&mut self, line: &str) {
+        self.src.push_str(line);
+        self.src.push_str("\r\n");
+    }
+
+    fn finish(mut self) -> String {
+        self.line("END:VCALENDAR");
+        self.src
+    }
+}
+
+/// A change of UTC offset, such as the start of summer time.
+#[derive(Debug, PartialEq, Eq)]
+struct Observance {
+    /// The local time at which the offset changes, by the clock before the change.
+    onset: NaiveDateTime,
+
+    /// The offsets before and after the change, in seconds east of UTC.
+    from: i32,
+    to: i32,
+}
+
+fn local_offset(utc: &NaiveDateTime) -> i32 {
+    Local
+        .from_utc_datetime(utc)
+        .offset()
+        .fix()
+        .local_minus_utc()
+}
+
+/// Find the changes of UTC offset during a year, given the offset at each UTC time.
+fn observances(year: i32, offset: impl Fn(&NaiveDateTime) -> i32) -> Vec<Observance> {
+    let Some(mut day) =
+        NaiveDate::from_ymd_opt(year, 1, 1).and_then(|date| date.and_hms_opt(0, 0, 0))
+    else {
+        return vec![];
+    };
+    let mut observances = vec![];
+    while day.year() == year {
+        let next = day + Duration::days(1);
+        let from = offset(&day);
+        if offset(&next) != from {
+            // Narrow the change down to the second.
+            let (mut before, mut after) = (day, next);
+            while after - before > Duration::seconds(1) {
+                let middle = before + (after - before) / 2;
+                if offset(&middle) == from {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            observances.push(Observance {
+                onset: after + Duration::seconds(from.into()),
+                from,
+                to: offset(&after),
+            });
+        }
+        day = next;
+    }
+    observances
+}
+
+/// The weekday of a date by its place in the month, such as `-1SU` for the last Sunday.
+fn weekday_in_month(date: &NaiveDate) -> String {
+    let weekday = &date.weekday().to_string()[..2].to_uppercase();
+    let next_month = date
+        .with_day(1)
+        .and_then(|first| first.checked_add_months(Months::new(1)));
+    if next_month.is_some_and(|next_month| *date + Duration::days(7) >= next_month) {
+        format!("-1{weekday}")
+    } else {
+        format!("{}{weekday}", (date.day() - 1) / 7 + 1)
+    }
+}
+
+/// Format a UTC offset in seconds as `+hhmm`.
+fn utc_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    format!("{sign}{:02}{:02}", offset / 3600, offset % 3600 / 60)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, NaiveTime, TimeZone};
+
+    use super::*;
+
+    fn settings() -> Settings {
+        let mut settings = Settings::default();
+        settings
+            .work_hours_mut()
+            .set_start(NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+        settings
+            .work_hours_mut()
+            .set_end(NaiveTime::from_hms_opt(17, 0, 0).unwrap());
+        settings
+    }
+
+    /// The lines of each event, without the time zone or the lines every event has.
+    fn events(ics: &str) -> Vec<Vec<String>> {
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        let mut events = vec![];
+        let mut lines = ics.lines().map(|line| match line.split_once(";TZID=") {
+            Some((name, rest)) => format!("{name}:{}", rest.split_once(':').unwrap().1),
+            None => line.into(),
+        });
+        while lines.any(|line| line == "BEGIN:VEVENT") {
+            events.push(
+                lines
+                    .by_ref()
+                    .take_while(|line| line != "END:VEVENT")
+                    .filter(|line| !line.starts_with("DTSTAMP:") && !line.starts_with("SUMMARY:"))
+                    .collect(),
+            );
+        }
+        events
+    }
+
+    #[test]
+    fn ics() {
+        let mut settings = settings();
+        // 2023-08-07 was a Monday.
+        settings.add_leave_day(NaiveDate::from_ymd_opt(2023, 8, 9).unwrap());
+        let now = Local.with_ymd_and_hms(2023, 8, 7, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            vec![vec![
+                "UID:work-hours@commute",
+                "DTSTART:20230807T090000",
+                "DTEND:20230807T170000",
+                "RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR",
+                "EXDATE:20230809T090000",
+            ]],
+            events(&super::ics(&settings, &now))
+        );
+    }
+
+    #[test]
+    fn ics_override() {
+        let mut settings = settings();
+        let now = Local.with_ymd_and_hms(2023, 8, 7, 12, 0, 0).unwrap();
+        let until = Local.with_ymd_and_hms(2023, 8, 7, 20, 0, 0).unwrap();
+        settings.set_override(
+            serde_yaml::from_str(&format!("profile-type: work\ndate: {}", until.timestamp()))
+                .unwrap(),
+        );
+        assert_eq!(
+            vec![
+                vec![
+                    "UID:work-hours@commute",
+                    "DTSTART:20230807T090000",
+                    "DTEND:20230807T170000",
+                    "RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR",
+                ],
+                vec![
+                    "UID:work-hours@commute",
+                    "RECURRENCE-ID:20230807T090000",
+                    "DTSTART:20230807T090000",
+                    "DTEND:20230807T200000",
+                ],
+            ],
+            events(&super::ics(&settings, &now))
+        );
+
+        // Work at the weekend is outside the series.
+        let now = Local.with_ymd_and_hms(2023, 8, 12, 10, 0, 0).unwrap();
+        let until = Local.with_ymd_and_hms(2023, 8, 12, 15, 0, 0).unwrap();
+        settings.set_override(
+            serde_yaml::from_str(&format!("profile-type: work\ndate: {}", until.timestamp()))
+                .unwrap(),
+        );
+        assert_eq!(
+            vec![
+                vec![
+                    "UID:work-hours@commute",
+                    "DTSTART:20230814T090000",
+                    "DTEND:20230814T170000",
+                    "RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR",
+                ],
+                vec![
+                    "UID:work-20230812T100000@commute",
+                    "DTSTART:20230812T100000",
+                    "DTEND:20230812T150000",
+                ],
+            ],
+            events(&super::ics(&settings, &now))
+        );
+    }
+
+    #[test]
+    fn time_zone() {
+        // Central European Time, with summer time from 01:00 UTC on the last Sunday of March to
+        // 01:00 UTC on the last Sunday of October.
+        let offset = |utc: &NaiveDateTime| {
+            let start = NaiveDate::from_ymd_opt(2023, 3, 26)
+                .unwrap()
+                .and_hms_opt(1, 0, 0);
+            let end = NaiveDate::from_ymd_opt(2023, 10, 29)
+                .unwrap()
+                .and_hms_opt(1, 0, 0);
+            if start.unwrap() <= *utc && *utc < end.unwrap() {
+                7200
+            } else {
+                3600
+            }
+        };
+        let mut calendar = Calendar {
+            src: String::new(),
+            stamp: String::new(),
+            tzid: None,
+        };
+        calendar.time_zone("Europe/Paris", 2023, offset);
+        assert_eq!(
+            [
+                "BEGIN:VTIMEZONE",
+                "TZID:Europe/Paris",
+                "BEGIN:DAYLIGHT",
+                "DTSTART:20230326T020000",
+                "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+                "TZOFFSETFROM:+0100",
+                "TZOFFSETTO:+0200",
+                "END:DAYLIGHT",
+                "BEGIN:STANDARD",
+                "DTSTART:20231029T030000",
+                "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+                "TZOFFSETFROM:+0200",
+                "TZOFFSETTO:+0100",
+                "END:STANDARD",
+                "END:VTIMEZONE",
+                "",
+            ]
+            .join("\r\n"),
+            calendar.src
+        );
+
+        let mut calendar = Calendar {
+            src: String::new(),
+            stamp: String::new(),
+            tzid: None,
+        };
+        calendar.time_zone("Asia/Kolkata", 2023, |_| 19800);
+        assert!(calendar
+            .src
+            .contains("TZOFFSETFROM:+0530\r\nTZOFFSETTO:+0530\r\n"));
+    }
+}
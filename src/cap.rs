@@ -0,0 +1,145 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Datelike, Duration, Local};
+use serde::{Deserialize as Deserialise, Serialize as Serialise};
+
+use crate::{
+    history::{self, HistoryEntry},
+    report,
+};
+
+/// Limits on the time spent in the work profile, in hours.
+#[derive(Debug, Default, Serialise, Deserialise)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct WorkTimeCap {
+    daily: Option<f64>,
+    weekly: Option<f64>,
+}
+
+/// A limit on work time which has been reached.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ReachedCap {
+    Daily(f64),
+    Weekly(f64),
+}
+
+impl WorkTimeCap {
+    /// The first cap which the work time recorded up to `now` has reached, if any. Time is only
+    /// counted while a profile was due to be in force, so a computer left off does not count.
+    pub(crate) fn reached(
+        &self,
+        history: &[HistoryEntry],
+        now: &DateTime<Local>,
+    ) -> Option<ReachedCap> {
+        if self.daily.is_none() && self.weekly.is_none() {
+            return None;
+        }
+
+        let today = now.date_naive();
+        let monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+        let days = report::daily_totals(&history::confirmed(history), monday, today, now);
+        let worked_today = days
+            .last()
+            .map(|day| day.work())
+            .unwrap_or_else(Duration::zero);
+        let worked_this_week = days
+            .iter()
+            .fold(Duration::zero(), |total, day| total + day.work());
+
+        if let Some(daily) = self.daily.filter(|daily| worked_today >= hours(*daily)) {
+            return Some(ReachedCap::Daily(daily));
+        }
+        self.weekly
+            .filter(|weekly| worked_this_week >= hours(*weekly))
+            .map(ReachedCap::Weekly)
+    }
+}
+
+impl Display for ReachedCap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Daily(hours) => write!(f, "daily work-time cap of {hours} hours"),
+            Self::Weekly(hours) => write!(f, "weekly work-time cap of {hours} hours"),
+        }
+    }
+}
+
+fn hours(hours: f64) -> Duration {
+    Duration::seconds((hours * 3600.0) as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{
+        settings::ProfileType,
+        transition::{Reason, Transition},
+    };
+
+    fn local(day: u32, hour: u32) -> DateTime<Local> {
+        // 2023-08-07 was a Monday.
+        Local
+            .with_ymd_and_hms(2023, 8, day, hour, 0, 0)
+            .single()
+            .unwrap()
+    }
+
+    #[test]
+    fn reached() {
+        let history = [
+            HistoryEntry::new(
+                local(7, 9),
+                &Transition::new(None, ProfileType::Work, Reason::Auto),
+                None,
+            ),
+            HistoryEntry::new(
+                local(7, 17),
+                &Transition::new(None, ProfileType::Home, Reason::Auto),
+                None,
+            ),
+            HistoryEntry::new(
+                local(8, 9),
+                &Transition::new(None, ProfileType::Work, Reason::Auto),
+                None,
+            )
+            .until(Some(local(8, 18))),
+        ];
+        let cap = WorkTimeCap {
+            daily: Some(7.5),
+            weekly: Some(12.0),
+        };
+        assert_eq!(None, cap.reached(&history, &local(8, 12)));
+        assert_eq!(
+            Some(ReachedCap::Weekly(12.0)),
+            cap.reached(&history, &local(8, 13))
+        );
+        assert_eq!(
+            Some(ReachedCap::Daily(7.5)),
+            cap.reached(&history, &local(8, 17))
+        );
+    }
+
+    #[test]
+    fn reached_after_weekend() {
+        // Nothing was recorded after work on Friday 2023-08-11, as the computer was off.
+        let friday = HistoryEntry::new(
+            local(11, 9),
+            &Transition::new(Some(ProfileType::Home), ProfileType::Work, Reason::Auto),
+            None,
+        );
+        let cap = WorkTimeCap {
+            daily: Some(8.0),
+            weekly: Some(40.0),
+        };
+        let monday = local(14, 9);
+        assert_eq!(
+            None,
+            cap.reached(&[friday.clone().until(Some(local(11, 17)))], &monday)
+        );
+
+        // Nor is time counted after an entry which does not say when it was due to switch.
+        assert_eq!(None, cap.reached(&[friday], &monday));
+    }
+}
@@ -1,8 +1,14 @@
+use anyhow::anyhow;
 use chrono::NaiveTime;
+use clap::ValueEnum;
 
 use crate::{
     args::{ConfigKey, WorkHoursTransition},
-    settings::{Profile, ProfileType, Settings, WorkHours},
+    browser,
+    desktop::DesktopKind,
+    error::Error,
+    result::Result,
+    settings::{Config, Profile, ProfileType, Settings, SettingsPaths, WorkHours},
 };
 
 #[derive(Debug)]
@@ -20,26 +26,37 @@ impl<'a> Configurator<'a> {
         use ConfigKey::*;
         match key {
             Browser => profile.browser(),
+            BrowserProfile => profile.browser_profile(),
             BackgroundDir => profile.background_dir(),
             GtkTheme => profile.theme().gtk(),
             IconTheme => profile.theme().icons(),
         }
     }
 
-    pub(crate) fn set(&mut self, profile_type: ProfileType, key: &ConfigKey, value: &str) {
+    pub(crate) fn set(
+        &mut self,
+        profile_type: ProfileType,
+        key: &ConfigKey,
+        value: &str,
+    ) -> Result<()> {
         let value = if value.to_lowercase() != "none" {
             Some(value.to_string())
         } else {
             None
         };
+        if let (ConfigKey::Browser, Some(browser)) = (key, &value) {
+            check_browser(browser)?;
+        }
         let profile = self.profile_mut(profile_type);
         use ConfigKey::*;
         match key {
             Browser => profile.set_browser(value),
+            BrowserProfile => profile.set_browser_profile(value),
             BackgroundDir => profile.set_background_dir(value),
             GtkTheme => profile.theme_mut().set_gtk(value),
             IconTheme => profile.theme_mut().set_icons(value),
         }
+        Ok(())
     }
 
     pub(crate) fn profile(&self, profile_type: ProfileType) -> &Profile {
@@ -75,7 +92,38 @@ impl<'a> Configurator<'a> {
         }
     }
 
-    pub(crate) fn settings(&self) -> &Settings {
-        &self.settings
+    pub(crate) fn desktop(&self) -> String {
+        match self.settings.desktop() {
+            Some(desktop) => format!("{desktop:?}").to_lowercase(),
+            None => format!("auto ({:?})", DesktopKind::detect()).to_lowercase(),
+        }
+    }
+
+    pub(crate) fn set_desktop(&mut self, desktop: &str) -> Result<()> {
+        let desktop = match desktop.to_lowercase().as_str() {
+            "auto" | "none" => None,
+            desktop => Some(DesktopKind::from_str(desktop, true).map_err(|e| anyhow!(e))?),
+        };
+        self.settings.set_desktop(desktop);
+        Ok(())
+    }
+
+    pub(crate) fn config(&self) -> &Config {
+        self.settings.config()
+    }
+
+    pub(crate) fn paths(&self) -> &SettingsPaths {
+        self.settings.paths()
+    }
+}
+
+fn check_browser(browser: &str) -> Result<()> {
+    let installed = browser::installed_browsers()?;
+    if installed.iter().any(|b| b == browser) {
+        return Ok(());
     }
+    Err(Error::UnknownBrowserError {
+        browser: browser.into(),
+        suggestions: browser::similar_browsers(browser, &installed),
+    })
 }
@@ -0,0 +1,204 @@
+mod gnome;
+mod kde;
+mod wlroots;
+mod xfce;
+
+use std::{env, fmt::Display};
+
+use clap::ValueEnum;
+use serde::{Deserialize as Deserialise, Serialize as Serialise};
+
+use crate::{error::Error, result::Result};
+
+pub(crate) use self::{gnome::Gnome, kde::Kde, wlroots::Wlroots, xfce::Xfce};
+
+/// A desktop environment which can have its appearance changed.
+pub(crate) trait Desktop {
+    /// The background currently shown, if this can be determined.
+    fn background(&self) -> Result<Option<Background>>;
+
+    fn set_background(&self, background: &Background) -> Result<()>;
+
+    /// The background of the lock screen, if this can be determined.
+    fn lock_screen_background(&self) -> Result<Option<String>> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "lock screen background",
+        })
+    }
+
+    fn set_lock_screen_background(&self, _path: &str) -> Result<()> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "lock screen background",
+        })
+    }
+
+    fn gtk_theme(&self) -> Result<Option<String>>;
+
+    fn set_gtk_theme(&self, theme: &str) -> Result<()>;
+
+    fn icon_theme(&self) -> Result<Option<String>>;
+
+    fn set_icon_theme(&self, theme: &str) -> Result<()>;
+
+    /// Whether notifications pop up as banners rather than only appearing in a list.
+    fn notification_banners(&self) -> Result<Option<bool>> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "notification banners",
+        })
+    }
+
+    fn set_notification_banners(&self, _show: bool) -> Result<()> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "notification banners",
+        })
+    }
+
+    /// Whether the app with the given desktop ID may send notifications.
+    fn app_notifications(&self, _app: &str) -> Result<Option<bool>> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "per-app notifications",
+        })
+    }
+
+    fn set_app_notifications(&self, _app: &str, _enabled: bool) -> Result<()> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "per-app notifications",
+        })
+    }
+
+    /// The desktop IDs of the apps pinned to the dock.
+    fn favourite_apps(&self) -> Result<Option<Vec<String>>> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "favourite apps",
+        })
+    }
+
+    fn set_favourite_apps(&self, _apps: &[String]) -> Result<()> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "favourite apps",
+        })
+    }
+
+    /// The UUIDs of all installed shell extensions.
+    fn installed_extensions(&self) -> Result<Vec<String>> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "shell extensions",
+        })
+    }
+
+    fn enabled_extensions(&self) -> Result<Option<Vec<String>>> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "shell extensions",
+        })
+    }
+
+    fn set_enabled_extensions(&self, _uuids: &[String]) -> Result<()> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "shell extensions",
+        })
+    }
+
+    fn disabled_extensions(&self) -> Result<Option<Vec<String>>> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "shell extensions",
+        })
+    }
+
+    fn set_disabled_extensions(&self, _uuids: &[String]) -> Result<()> {
+        Err(Error::UnsupportedByDesktopError {
+            feature: "shell extensions",
+        })
+    }
+
+    /// Wait for all changes to be written.
+    fn sync(&self) {}
+}
+
+/// A desktop background, which may be a different image in the light and dark styles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Background {
+    pub(crate) light: String,
+    pub(crate) dark: String,
+}
+
+impl From<String> for Background {
+    fn from(uri: String) -> Self {
+        Self {
+            light: uri.clone(),
+            dark: uri,
+        }
+    }
+}
+
+impl Display for Background {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.light == self.dark {
+            write!(f, "{}", self.dark)
+        } else {
+            write!(f, "{} (light), {} (dark)", self.light, self.dark)
+        }
+    }
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[warn(missing_docs)]
+pub(crate) enum DesktopKind {
+    Gnome,
+    Kde,
+    Xfce,
+    Wlroots,
+}
+
+impl DesktopKind {
+    /// Guess the running desktop from `XDG_CURRENT_DESKTOP`, falling back to GNOME.
+    pub(crate) fn detect() -> Self {
+        env::var("XDG_CURRENT_DESKTOP")
+            .ok()
+            .and_then(|desktops| Self::from_xdg_current_desktop(&desktops))
+            .unwrap_or(Self::Gnome)
+    }
+
+    fn from_xdg_current_desktop(desktops: &str) -> Option<Self> {
+        desktops
+            .split(':')
+            .find_map(|desktop| match desktop.to_lowercase().as_str() {
+                "gnome" | "gnome-classic" | "unity" | "budgie" | "pantheon" => Some(Self::Gnome),
+                "kde" => Some(Self::Kde),
+                "xfce" => Some(Self::Xfce),
+                "sway" | "hyprland" | "river" | "wlroots" => Some(Self::Wlroots),
+                _ => None,
+            })
+    }
+
+    pub(crate) fn backend(&self) -> Box<dyn Desktop> {
+        match self {
+            Self::Gnome => Box::new(Gnome),
+            Self::Kde => Box::new(Kde),
+            Self::Xfce => Box::new(Xfce),
+            Self::Wlroots => Box::new(Wlroots),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_xdg_current_desktop() {
+        let cases = [
+            ("GNOME", Some(DesktopKind::Gnome)),
+            ("ubuntu:GNOME", Some(DesktopKind::Gnome)),
+            ("KDE", Some(DesktopKind::Kde)),
+            ("XFCE", Some(DesktopKind::Xfce)),
+            ("sway", Some(DesktopKind::Wlroots)),
+            ("Hyprland", Some(DesktopKind::Wlroots)),
+            ("", None),
+            ("LXQt", None),
+        ];
+        for (raw, kind) in cases {
+            assert_eq!(kind, DesktopKind::from_xdg_current_desktop(raw), "{raw}");
+        }
+    }
+}
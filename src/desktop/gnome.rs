@@ -0,0 +1,290 @@
+use std::{fmt::Display, fs};
+
+use anyhow::Context;
+use gio::prelude::{SettingsExt, SettingsExtManual};
+
+use crate::{
+    desktop::{Background, Desktop},
+    error::Error,
+    result::Result,
+    xdg,
+};
+
+const BACKGROUND_SCHEMA: &str = "org.gnome.desktop.background";
+const INTERFACE_SCHEMA: &str = "org.gnome.desktop.interface";
+const NOTIFICATIONS_SCHEMA: &str = "org.gnome.desktop.notifications";
+const APP_NOTIFICATIONS_SCHEMA: &str = "org.gnome.desktop.notifications.application";
+const APP_NOTIFICATIONS_PATH: &str = "/org/gnome/desktop/notifications/application";
+const SHELL_SCHEMA: &str = "org.gnome.shell";
+const SCREENSAVER_SCHEMA: &str = "org.gnome.desktop.screensaver";
+
+/// GNOME and other desktops which read their appearance from GSettings.
+pub(crate) struct Gnome;
+
+impl Gnome {
+    /// Open the settings for a schema, checking first that it is installed and has the given keys,
+    /// as GIO aborts the process otherwise.
+    pub(crate) fn settings(schema_id: &str, keys: &[&str]) -> Result<gio::Settings> {
+        Self::settings_at(schema_id, None, keys)
+    }
+
+    /// Open the settings for a relocatable schema at the given path.
+    fn settings_at(schema_id: &str, path: Option<&str>, keys: &[&str]) -> Result<gio::Settings> {
+        let schema = gio::SettingsSchemaSource::default()
+            .and_then(|source| source.lookup(schema_id, true))
+            .ok_or_else(|| Error::MissingSchemaError {
+                schema: schema_id.into(),
+            })?;
+        if let Some(key) = keys.iter().find(|key| !schema.has_key(key)) {
+            return Err(Error::MissingSchemaKeyError {
+                schema: schema_id.into(),
+                key: key.to_string(),
+            });
+        }
+        Ok(gio::Settings::new_full(
+            &schema,
+            None::<&gio::SettingsBackend>,
+            path,
+        ))
+    }
+
+    fn strv(schema_id: &str, key: &str) -> Result<Option<Vec<String>>> {
+        let settings = Self::settings(schema_id, &[key])?;
+        Ok(Some(
+            settings
+                .strv(key)
+                .iter()
+                .map(|value| value.to_string())
+                .collect(),
+        ))
+    }
+
+    fn set_strv(schema_id: &str, key: &str, values: &[String]) -> Result<()> {
+        let values: Vec<&str> = values.iter().map(String::as_str).collect();
+        write_strv(&Self::settings(schema_id, &[key])?, key, &values)
+    }
+
+    /// The notification settings of the app with the given desktop ID, registering the app with
+    /// GNOME Shell if it has not sent a notification yet.
+    fn app_notification_settings(app: &str) -> Result<gio::Settings> {
+        let app_id = app_notifications_id(app);
+        let notification_settings =
+            Self::settings(NOTIFICATIONS_SCHEMA, &["application-children"])?;
+        let mut children = notification_settings.strv("application-children");
+        if !children.iter().any(|child| child.to_str() == app_id) {
+            children.push(app_id.as_str().into());
+            let children: Vec<&str> = children.iter().map(|child| child.to_str()).collect();
+            write_strv(&notification_settings, "application-children", &children)?;
+        }
+
+        let app_settings = Self::settings_at(
+            APP_NOTIFICATIONS_SCHEMA,
+            Some(&format!("{APP_NOTIFICATIONS_PATH}/{app_id}/")),
+            &["enable", "application-id"],
+        )?;
+        write_string(&app_settings, "application-id", app)?;
+        Ok(app_settings)
+    }
+}
+
+fn write_string(settings: &gio::Settings, key: &str, value: &str) -> Result<()> {
+    settings
+        .set_string(key, value)
+        .context(format!("failed to set {key}"))?;
+    log_write(settings, key, value);
+    Ok(())
+}
+
+fn write_boolean(settings: &gio::Settings, key: &str, value: bool) -> Result<()> {
+    settings
+        .set_boolean(key, value)
+        .context(format!("failed to set {key}"))?;
+    log_write(settings, key, value);
+    Ok(())
+}
+
+fn write_strv(settings: &gio::Settings, key: &str, values: &[&str]) -> Result<()> {
+    settings
+        .set_strv(key, values)
+        .context(format!("failed to set {key}"))?;
+    log_write(settings, key, values.join(","));
+    Ok(())
+}
+
+fn log_write(settings: &gio::Settings, key: &str, value: impl Display) {
+    log::debug!(
+        schema = settings.schema_id().unwrap_or_default().as_str(),
+        path = settings.path().unwrap_or_default().as_str(),
+        key,
+        value:% = value;
+        "wrote gsettings key"
+    );
+}
+
+/// The ID under which GNOME Shell stores an app's notification settings.
+fn app_notifications_id(app: &str) -> String {
+    let mut id = String::new();
+    for c in app.trim_end_matches(".desktop").to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c);
+        } else if !id.ends_with('-') {
+            id.push('-');
+        }
+    }
+    id
+}
+
+impl Desktop for Gnome {
+    fn background(&self) -> Result<Option<Background>> {
+        let background_settings =
+            Self::settings(BACKGROUND_SCHEMA, &["picture-uri", "picture-uri-dark"])?;
+        Ok(Some(Background {
+            light: background_settings.string("picture-uri").to_string(),
+            dark: background_settings.string("picture-uri-dark").to_string(),
+        }))
+    }
+
+    fn set_background(&self, background: &Background) -> Result<()> {
+        let background_settings =
+            Self::settings(BACKGROUND_SCHEMA, &["picture-uri", "picture-uri-dark"])?;
+        write_string(&background_settings, "picture-uri", &background.light)?;
+        write_string(&background_settings, "picture-uri-dark", &background.dark)
+    }
+
+    fn lock_screen_background(&self) -> Result<Option<String>> {
+        let screensaver_settings = Self::settings(SCREENSAVER_SCHEMA, &["picture-uri"])?;
+        Ok(Some(screensaver_settings.string("picture-uri").to_string()))
+    }
+
+    fn set_lock_screen_background(&self, path: &str) -> Result<()> {
+        write_string(
+            &Self::settings(SCREENSAVER_SCHEMA, &["picture-uri"])?,
+            "picture-uri",
+            path,
+        )
+    }
+
+    fn gtk_theme(&self) -> Result<Option<String>> {
+        let interface_settings = Self::settings(INTERFACE_SCHEMA, &["gtk-theme"])?;
+        Ok(Some(interface_settings.string("gtk-theme").to_string()))
+    }
+
+    fn set_gtk_theme(&self, theme: &str) -> Result<()> {
+        write_string(
+            &Self::settings(INTERFACE_SCHEMA, &["gtk-theme"])?,
+            "gtk-theme",
+            theme,
+        )
+    }
+
+    fn icon_theme(&self) -> Result<Option<String>> {
+        let interface_settings = Self::settings(INTERFACE_SCHEMA, &["icon-theme"])?;
+        Ok(Some(interface_settings.string("icon-theme").to_string()))
+    }
+
+    fn set_icon_theme(&self, theme: &str) -> Result<()> {
+        write_string(
+            &Self::settings(INTERFACE_SCHEMA, &["icon-theme"])?,
+            "icon-theme",
+            theme,
+        )
+    }
+
+    fn notification_banners(&self) -> Result<Option<bool>> {
+        let notification_settings = Self::settings(NOTIFICATIONS_SCHEMA, &["show-banners"])?;
+        Ok(Some(notification_settings.boolean("show-banners")))
+    }
+
+    fn set_notification_banners(&self, show: bool) -> Result<()> {
+        write_boolean(
+            &Self::settings(NOTIFICATIONS_SCHEMA, &["show-banners"])?,
+            "show-banners",
+            show,
+        )
+    }
+
+    fn app_notifications(&self, app: &str) -> Result<Option<bool>> {
+        let app_id = app_notifications_id(app);
+        let known = Self::settings(NOTIFICATIONS_SCHEMA, &["application-children"])?
+            .strv("application-children")
+            .iter()
+            .any(|child| child.to_str() == app_id);
+        if !known {
+            // Apps are allowed to send notifications until told otherwise.
+            return Ok(Some(true));
+        }
+        let app_settings = Self::settings_at(
+            APP_NOTIFICATIONS_SCHEMA,
+            Some(&format!("{APP_NOTIFICATIONS_PATH}/{app_id}/")),
+            &["enable"],
+        )?;
+        Ok(Some(app_settings.boolean("enable")))
+    }
+
+    fn set_app_notifications(&self, app: &str, enabled: bool) -> Result<()> {
+        write_boolean(&Self::app_notification_settings(app)?, "enable", enabled)
+    }
+
+    fn favourite_apps(&self) -> Result<Option<Vec<String>>> {
+        Self::strv(SHELL_SCHEMA, "favorite-apps")
+    }
+
+    fn set_favourite_apps(&self, apps: &[String]) -> Result<()> {
+        Self::set_strv(SHELL_SCHEMA, "favorite-apps", apps)
+    }
+
+    fn installed_extensions(&self) -> Result<Vec<String>> {
+        let mut uuids = vec![];
+        for dir in xdg::data_dirs() {
+            let Ok(entries) = fs::read_dir(dir.join("gnome-shell").join("extensions")) else {
+                continue;
+            };
+            uuids.extend(
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string()),
+            );
+        }
+        Ok(uuids)
+    }
+
+    fn enabled_extensions(&self) -> Result<Option<Vec<String>>> {
+        Self::strv(SHELL_SCHEMA, "enabled-extensions")
+    }
+
+    fn set_enabled_extensions(&self, uuids: &[String]) -> Result<()> {
+        Self::set_strv(SHELL_SCHEMA, "enabled-extensions", uuids)
+    }
+
+    fn disabled_extensions(&self) -> Result<Option<Vec<String>>> {
+        Self::strv(SHELL_SCHEMA, "disabled-extensions")
+    }
+
+    fn set_disabled_extensions(&self, uuids: &[String]) -> Result<()> {
+        Self::set_strv(SHELL_SCHEMA, "disabled-extensions", uuids)
+    }
+
+    fn sync(&self) {
+        gio::Settings::sync();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn app_notifications_id() {
+        assert_eq!(
+            "org-gnome-nautilus",
+            super::app_notifications_id("org.gnome.Nautilus.desktop")
+        );
+        assert_eq!(
+            "com-slack-slack",
+            super::app_notifications_id("com.slack.Slack.desktop")
+        );
+        assert_eq!(
+            "google-chrome-beta",
+            super::app_notifications_id("google-chrome--beta.desktop")
+        );
+        assert_eq!("a-b", super::app_notifications_id("a._b"));
+    }
+}
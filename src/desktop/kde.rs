@@ -0,0 +1,122 @@
+use std::{fs, process::Command};
+
+use directories::BaseDirs;
+
+use crate::{
+    desktop::{Background, Desktop},
+    process::{self, in_path},
+    result::Result,
+};
+
+/// Where Plasma keeps the desktop containments, along with their wallpapers.
+const APPLETS_FILE: &str = "plasma-org.kde.plasma.desktop-appletsrc";
+
+/// KDE Plasma, configured through its command-line tools.
+pub(crate) struct Kde;
+
+impl Kde {
+    fn kreadconfig(&self, file: &str, group: &str, key: &str) -> Result<Option<String>> {
+        let value = process::output(
+            Command::new(Self::tool("kreadconfig"))
+                .args(["--file", file])
+                .args(["--group", group])
+                .args(["--key", key]),
+        )?;
+        Ok(Some(value).filter(|value| !value.is_empty()))
+    }
+
+    fn kwriteconfig(&self, file: &str, group: &str, key: &str, value: &str) -> Result<()> {
+        process::run(
+            Command::new(Self::tool("kwriteconfig"))
+                .args(["--file", file])
+                .args(["--group", group])
+                .args(["--key", key])
+                .arg(value),
+        )
+    }
+
+    /// The name of a configuration tool from either Plasma 6 or Plasma 5.
+    fn tool(name: &str) -> String {
+        let plasma6 = format!("{name}6");
+        if in_path(&plasma6) {
+            plasma6
+        } else {
+            format!("{name}5")
+        }
+    }
+}
+
+impl Desktop for Kde {
+    fn background(&self) -> Result<Option<Background>> {
+        let Some(path) = BaseDirs::new().map(|dirs| dirs.config_dir().join(APPLETS_FILE)) else {
+            return Ok(None);
+        };
+        let Ok(applets) = fs::read_to_string(path) else {
+            return Ok(None);
+        };
+        Ok(wallpaper(&applets).map(Background::from))
+    }
+
+    fn set_background(&self, background: &Background) -> Result<()> {
+        process::run(Command::new("plasma-apply-wallpaperimage").arg(&background.dark))
+    }
+
+    fn gtk_theme(&self) -> Result<Option<String>> {
+        self.kreadconfig("gtk-3.0/settings.ini", "Settings", "gtk-theme-name")
+    }
+
+    fn set_gtk_theme(&self, theme: &str) -> Result<()> {
+        self.kwriteconfig("gtk-3.0/settings.ini", "Settings", "gtk-theme-name", theme)
+    }
+
+    fn icon_theme(&self) -> Result<Option<String>> {
+        self.kreadconfig("kdeglobals", "Icons", "Theme")
+    }
+
+    fn set_icon_theme(&self, theme: &str) -> Result<()> {
+        self.kwriteconfig("kdeglobals", "Icons", "Theme", theme)
+    }
+}
+
+/// The image shown by the first desktop containment with one. Plasma stores a wallpaper per
+/// containment, but `plasma-apply-wallpaperimage` sets them all alike.
+fn wallpaper(applets: &str) -> Option<String> {
+    let mut in_image_group = false;
+    for line in applets.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_image_group = line.starts_with("[Containments]")
+                && line.ends_with("[Wallpaper][org.kde.image][General]");
+        } else if let Some(image) = line.strip_prefix("Image=").filter(|_| in_image_group) {
+            return Some(image.strip_prefix("file://").unwrap_or(image).into());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn wallpaper() {
+        let applets = "\
+[Containments][1]
+activityId=
+formfactor=2
+plugin=org.kde.panel
+
+[Containments][1][General]
+Image=file:///not/a/wallpaper.png
+
+[Containments][2][Wallpaper][org.kde.image][General]
+Image=file:///backgrounds/work/lake.jpg
+SlidePaths=/usr/share/wallpapers/
+";
+        assert_eq!(
+            Some("/backgrounds/work/lake.jpg".into()),
+            super::wallpaper(applets)
+        );
+        assert_eq!(
+            None,
+            super::wallpaper("[Containments][1]\nplugin=org.kde.panel\n")
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::process::{Command, Stdio};
+
+use crate::{
+    desktop::{Background, Desktop, Gnome},
+    process::{self, in_path},
+    result::Result,
+};
+
+/// Wlroots-based compositors such as sway and Hyprland.
+///
+/// Backgrounds are drawn by `swww` if it is installed, otherwise by `swaybg`. Themes are read by
+/// GTK from GSettings, as under GNOME.
+pub(crate) struct Wlroots;
+
+impl Desktop for Wlroots {
+    fn background(&self) -> Result<Option<Background>> {
+        if !in_path("swww") {
+            return Ok(None);
+        }
+        let query = process::output(Command::new("swww").arg("query"))?;
+        Ok(query
+            .lines()
+            .find_map(|line| line.split_once("image: "))
+            .map(|(_, path)| path.trim().to_string().into()))
+    }
+
+    fn set_background(&self, background: &Background) -> Result<()> {
+        let path = &background.dark;
+        if in_path("swww") {
+            return process::run(Command::new("swww").arg("img").arg(path));
+        }
+
+        // swaybg keeps running to draw the background, so replace any existing instance.
+        let _ = Command::new("pkill").args(["-x", "swaybg"]).status();
+        Command::new("swaybg")
+            .args(["-m", "fill", "-i", path])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        Ok(())
+    }
+
+    fn gtk_theme(&self) -> Result<Option<String>> {
+        Gnome.gtk_theme()
+    }
+
+    fn set_gtk_theme(&self, theme: &str) -> Result<()> {
+        Gnome.set_gtk_theme(theme)
+    }
+
+    fn icon_theme(&self) -> Result<Option<String>> {
+        Gnome.icon_theme()
+    }
+
+    fn set_icon_theme(&self, theme: &str) -> Result<()> {
+        Gnome.set_icon_theme(theme)
+    }
+
+    fn sync(&self) {
+        Gnome.sync();
+    }
+}
@@ -0,0 +1,84 @@
+use std::process::Command;
+
+use crate::{
+    desktop::{Background, Desktop},
+    process,
+    result::Result,
+};
+
+const DEFAULT_BACKDROP_PROPERTY: &str = "/backdrop/screen0/monitor0/workspace0/last-image";
+
+/// XFCE, configured through `xfconf-query`.
+pub(crate) struct Xfce;
+
+impl Xfce {
+    fn backdrop_properties(&self) -> Result<Vec<String>> {
+        let properties =
+            process::output(Command::new("xfconf-query").args(["-c", "xfce4-desktop", "-l"]))?;
+        Ok(properties
+            .lines()
+            .filter(|property| property.ends_with("/last-image"))
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    fn get(&self, channel: &str, property: &str) -> Result<Option<String>> {
+        let output = Command::new("xfconf-query")
+            .args(["-c", channel])
+            .args(["-p", property])
+            .output()?;
+        if !output.status.success() {
+            // The property does not exist yet.
+            return Ok(None);
+        }
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    }
+
+    fn set(&self, channel: &str, property: &str, value: &str) -> Result<()> {
+        process::run(
+            Command::new("xfconf-query")
+                .args(["-c", channel])
+                .args(["-p", property])
+                .args(["--create", "--type", "string"])
+                .args(["-s", value]),
+        )
+    }
+}
+
+impl Desktop for Xfce {
+    fn background(&self) -> Result<Option<Background>> {
+        let Some(property) = self.backdrop_properties()?.into_iter().next() else {
+            return Ok(None);
+        };
+        Ok(self.get("xfce4-desktop", &property)?.map(Background::from))
+    }
+
+    fn set_background(&self, background: &Background) -> Result<()> {
+        let mut properties = self.backdrop_properties()?;
+        if properties.is_empty() {
+            properties.push(DEFAULT_BACKDROP_PROPERTY.into());
+        }
+        for property in properties {
+            self.set("xfce4-desktop", &property, &background.dark)?;
+        }
+        Ok(())
+    }
+
+    fn gtk_theme(&self) -> Result<Option<String>> {
+        self.get("xsettings", "/Net/ThemeName")
+    }
+
+    fn set_gtk_theme(&self, theme: &str) -> Result<()> {
+        self.set("xsettings", "/Net/ThemeName", theme)
+    }
+
+    fn icon_theme(&self) -> Result<Option<String>> {
+        self.get("xsettings", "/Net/IconThemeName")
+    }
+
+    fn set_icon_theme(&self, theme: &str) -> Result<()> {
+        self.set("xsettings", "/Net/IconThemeName", theme)
+    }
+}
@@ -0,0 +1,128 @@
+use std::{collections::BTreeMap, fmt::Display, fs, path::PathBuf};
+
+use directories::{BaseDirs, ProjectDirs};
+use lazy_static::lazy_static;
+
+use crate::result::Result;
+
+lazy_static! {
+    /// Read by systemd when starting a user session.
+    static ref ENVIRONMENT_D_PATH: PathBuf = {
+        BaseDirs::new()
+            .unwrap()
+            .config_dir()
+            .join("environment.d")
+            .join("60-commute.conf")
+    };
+
+    /// For shells to source.
+    static ref SHELL_PATH: PathBuf = {
+        ProjectDirs::from("net", "kcza", env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .config_dir()
+            .join("env.sh")
+    };
+}
+
+/// Environment variables set by a profile, by name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Environment(BTreeMap<String, String>);
+
+impl Environment {
+    /// The variables most recently written by commute, if any.
+    pub(crate) fn current() -> Result<Option<Self>> {
+        if !ENVIRONMENT_D_PATH.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&*ENVIRONMENT_D_PATH)?;
+        Ok(Some(Self(
+            contents
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .filter_map(|line| line.split_once('='))
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        )))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Write these variables for new sessions and shells.
+    pub(crate) fn apply(&self) -> Result<()> {
+        for path in [&*ENVIRONMENT_D_PATH, &*SHELL_PATH] {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        let environment_d: String = self
+            .0
+            .iter()
+            .map(|(name, value)| format!("{name}={value}\n"))
+            .collect();
+        fs::write(&*ENVIRONMENT_D_PATH, environment_d)?;
+        fs::write(&*SHELL_PATH, self.exports(&[]))?;
+        Ok(())
+    }
+
+    /// Shell commands which set these variables, unsetting any of the given ones they omit.
+    pub(crate) fn exports(&self, others: &[&str]) -> String {
+        let mut exports = String::new();
+        for name in others {
+            if !self.0.contains_key(*name) {
+                exports += &format!("unset {name}\n");
+            }
+        }
+        for (name, value) in &self.0 {
+            exports += &format!("export {name}={}\n", quote(value));
+        }
+        exports
+    }
+}
+
+impl From<BTreeMap<String, String>> for Environment {
+    fn from(value: BTreeMap<String, String>) -> Self {
+        Self(value)
+    }
+}
+
+impl Display for Environment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let variables: Vec<_> = self
+            .0
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        write!(f, "[{}]", variables.join(", "))
+    }
+}
+
+/// Quote a value for the shell, leaving `$NAME` references to be expanded as systemd would.
+fn quote(value: &str) -> String {
+    let escaped: String = value
+        .chars()
+        .flat_map(|c| match c {
+            '"' | '`' | '\\' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect();
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exports() {
+        let environment = Environment::from(BTreeMap::from([
+            ("AWS_PROFILE".to_string(), "work".to_string()),
+            ("PATH".to_string(), "$PATH:/opt/\"corp\"/bin".to_string()),
+        ]));
+        assert_eq!(
+            "unset KUBECONFIG\nexport AWS_PROFILE=\"work\"\nexport PATH=\"$PATH:/opt/\\\"corp\\\"/bin\"\n",
+            environment.exports(&["AWS_PROFILE", "KUBECONFIG"])
+        );
+    }
+}
@@ -1,7 +1,10 @@
-use std::fmt::Display;
+use std::{fmt::Display, path::PathBuf, process::ExitCode, time::Duration};
+
+use crate::cap::ReachedCap;
 
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum Error {
     #[error("io error: {0}")]
     IOError(#[from] std::io::Error),
@@ -9,26 +12,155 @@ pub(crate) enum Error {
     #[error("json conversion error: {0}")]
     YAMLError(#[from] serde_yaml::Error),
 
+    #[error("json conversion error: {0}")]
+    JSONError(#[from] serde_json::Error),
+
     #[error("{0}: {}", .0.root_cause())]
     AnyHowError(#[from] anyhow::Error),
 
     #[error("glib: {0}")]
     GLibBoolError(#[from] gio::glib::error::BoolError),
 
+    #[error("glib: {0}")]
+    GLibError(#[from] gio::glib::Error),
+
     #[error("failed to execute `{name}`: {reason}")]
     ChildProcessError {
         name: String,
         reason: ChildProcessExit,
     },
 
+    #[error("the {cap} has been reached, pass --force to work anyway")]
+    WorkTimeCapError { cap: ReachedCap },
+
+    #[error(
+        "{} needs settings version {version}, but this version of commute only supports up to {supported}, please upgrade commute",
+        .path.display()
+    )]
+    SettingsVersionError {
+        path: PathBuf,
+        version: u64,
+        supported: u64,
+    },
+
     #[error("failed to parse time: {0}")]
     TimeParseError(#[from] chrono::format::ParseError),
+
+    #[error("gsettings schema `{schema}` is not installed")]
+    MissingSchemaError { schema: String },
+
+    #[error("gsettings schema `{schema}` has no key `{key}`")]
+    MissingSchemaKeyError { schema: String, key: String },
+
+    #[error("{feature} cannot be changed on this desktop")]
+    UnsupportedByDesktopError { feature: &'static str },
+
+    #[error("no application found for `{id}`")]
+    UnknownAppError { id: String },
+
+    #[error("unknown browser `{browser}`{}", did_you_mean(.suggestions))]
+    UnknownBrowserError {
+        browser: String,
+        suggestions: Vec<String>,
+    },
+
+    #[error("cannot select a profile in `{browser}`, only firefox- and chromium-based browsers are supported")]
+    UnsupportedBrowserError { browser: String },
+
+    #[error("{failures}")]
+    ApplyError {
+        failures: StepFailures,
+        partial: bool,
+    },
+}
+
+impl Error {
+    /// Whether this error means the desktop lacks a setting, rather than failing to change it.
+    pub(crate) fn is_unsupported(&self) -> bool {
+        matches!(
+            self,
+            Self::MissingSchemaError { .. }
+                | Self::MissingSchemaKeyError { .. }
+                | Self::UnsupportedByDesktopError { .. }
+        )
+    }
+
+    /// The exit code to report, which distinguishes a profile left partially applied.
+    pub(crate) fn exit_code(&self) -> ExitCode {
+        match self {
+            Self::ApplyError { partial: true, .. } => ExitCode::from(2),
+            _ => ExitCode::FAILURE,
+        }
+    }
+}
+
+fn did_you_mean(suggestions: &[String]) -> String {
+    match suggestions {
+        [] => String::new(),
+        [suggestion] => format!(", did you mean `{suggestion}`?"),
+        suggestions => format!(
+            ", did you mean one of: {}?",
+            suggestions
+                .iter()
+                .map(|s| format!("`{s}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct StepFailures(Vec<StepFailure>);
+
+impl From<Vec<StepFailure>> for StepFailures {
+    fn from(value: Vec<StepFailure>) -> Self {
+        Self(value)
+    }
+}
+
+impl Display for StepFailures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, failure) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{failure}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The reason one step of applying a profile failed.
+#[derive(Debug)]
+pub(crate) struct StepFailure {
+    step: String,
+    error: Box<Error>,
+}
+
+impl StepFailure {
+    pub(crate) fn new(step: impl Into<String>, error: Error) -> Self {
+        Self {
+            step: step.into(),
+            error: Box::new(error),
+        }
+    }
+
+    pub(crate) fn step(&self) -> &str {
+        &self.step
+    }
+}
+
+impl Display for StepFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to set {}: {}", self.step, self.error)
+    }
 }
 
 #[derive(Debug)]
 pub(crate) enum ChildProcessExit {
     Exit(i32),
     Signal,
+    Timeout(Duration),
 }
 
 impl From<Option<i32>> for ChildProcessExit {
@@ -45,6 +177,7 @@ impl Display for ChildProcessExit {
         match self {
             Self::Exit(c) => write!(f, "process exited with status code: {c}"),
             Self::Signal => write!(f, "process terminated by signal"),
+            Self::Timeout(t) => write!(f, "process timed out after {}s", t.as_secs()),
         }
     }
 }
@@ -0,0 +1,124 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use directories::ProjectDirs;
+use lazy_static::lazy_static;
+use serde::{Deserialize as Deserialise, Serialize as Serialise};
+
+use crate::{process, result::Result};
+
+lazy_static! {
+    /// The file which holds the identity of the current profile, included by the user's gitconfig.
+    static ref IDENTITY_PATH: PathBuf = {
+        ProjectDirs::from("net", "kcza", env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .config_dir()
+            .join("git")
+            .join("current.gitconfig")
+    };
+}
+
+/// The author details git uses for commits.
+#[derive(Clone, Debug, Default, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct GitIdentity {
+    name: Option<String>,
+    email: Option<String>,
+    signing_key: Option<String>,
+}
+
+impl GitIdentity {
+    /// The identity most recently applied by commute, if any.
+    pub(crate) fn current() -> Result<Option<Self>> {
+        if !IDENTITY_PATH.exists() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            name: get("user.name")?,
+            email: get("user.email")?,
+            signing_key: get("user.signingkey")?,
+        }))
+    }
+
+    /// Write this identity to the included gitconfig.
+    pub(crate) fn apply(&self) -> Result<()> {
+        if let Some(dir) = IDENTITY_PATH.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&*IDENTITY_PATH, "")?;
+        for (key, value) in [
+            ("user.name", &self.name),
+            ("user.email", &self.email),
+            ("user.signingkey", &self.signing_key),
+        ] {
+            if let Some(value) = value {
+                process::run(
+                    Command::new("git")
+                        .arg("config")
+                        .arg("--file")
+                        .arg(&*IDENTITY_PATH)
+                        .args(["--", key, value]),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for GitIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name.as_deref().unwrap_or("(no name)"))?;
+        if let Some(email) = &self.email {
+            write!(f, " <{email}>")?;
+        }
+        if let Some(signing_key) = &self.signing_key {
+            write!(f, " signing with {signing_key}")?;
+        }
+        Ok(())
+    }
+}
+
+fn get(key: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .arg("config")
+        .arg("--file")
+        .arg(&*IDENTITY_PATH)
+        .args(["--get", key])
+        .output()?;
+    // git exits with status 1 if the key is unset.
+    Ok(
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|value| output.status.success() && !value.is_empty()),
+    )
+}
+
+/// The file which holds the identity of the current profile.
+pub(crate) fn identity_path() -> &'static Path {
+    &IDENTITY_PATH
+}
+
+/// Reference the identity file from the user's gitconfig, unless it already is.
+pub(crate) fn install_include() -> Result<()> {
+    let identity_path = IDENTITY_PATH.to_string_lossy();
+    let output = Command::new("git")
+        .args(["config", "--global", "--get-all", "include.path"])
+        .output()?;
+    if String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|path| path == identity_path)
+    {
+        return Ok(());
+    }
+    process::run(Command::new("git").args([
+        "config",
+        "--global",
+        "--add",
+        "--",
+        "include.path",
+        &identity_path,
+    ]))
+}
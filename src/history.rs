@@ -0,0 +1,234 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use serde::{Deserialize as Deserialise, Serialize as Serialise};
+
+use crate::{
+    result::Result,
+    settings::ProfileType,
+    transition::{Reason, Transition},
+};
+
+/// A record of a profile being applied.
+#[derive(Clone, Debug, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct HistoryEntry {
+    time: DateTime<Local>,
+    from: Option<ProfileType>,
+    to: ProfileType,
+    reason: Reason,
+
+    /// Why the user made the change, as given by `--reason`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Outcome::is_applied")]
+    outcome: Outcome,
+
+    /// When the profile was next due to switch, after which it is not counted as in force unless
+    /// a later entry says so, as `commute` may not have run to record the switch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    until: Option<DateTime<Local>>,
+}
+
+/// What became of a request for a profile.
+#[derive(Clone, Copy, Debug, Default, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Outcome {
+    #[default]
+    Applied,
+
+    /// A work override was refused as the work-time cap was reached.
+    Refused,
+
+    /// A work override was applied despite the work-time cap being reached.
+    Forced,
+}
+
+impl Outcome {
+    fn is_applied(&self) -> bool {
+        *self == Self::Applied
+    }
+}
+
+impl HistoryEntry {
+    pub(crate) fn new(time: DateTime<Local>, transition: &Transition, note: Option<&str>) -> Self {
+        Self {
+            time,
+            from: transition.from(),
+            to: transition.to(),
+            reason: transition.reason(),
+            note: note.map(Into::into),
+            outcome: Outcome::Applied,
+            until: None,
+        }
+    }
+
+    pub(crate) fn outcome(mut self, outcome: Outcome) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    /// Note when the profile is next due to switch.
+    pub(crate) fn until(mut self, until: Option<DateTime<Local>>) -> Self {
+        self.until = until;
+        self
+    }
+
+    pub(crate) fn time(&self) -> &DateTime<Local> {
+        &self.time
+    }
+}
+
+/// The periods spent in the given profile. Each profile applied is counted as in force until the
+/// next entry, `now` or when it was due to switch, whichever is first.
+pub(crate) fn periods(
+    history: &[HistoryEntry],
+    profile_type: ProfileType,
+    now: &DateTime<Local>,
+) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+    let applied: Vec<_> = history
+        .iter()
+        .filter(|entry| entry.outcome != Outcome::Refused)
+        .collect();
+    let ends = applied.iter().skip(1).map(|entry| entry.time).chain([*now]);
+    applied
+        .iter()
+        .zip(ends)
+        .map(|(entry, end)| (entry, entry.until.map_or(end, |until| until.min(end))))
+        .filter(|(entry, end)| entry.to == profile_type && entry.time < *end)
+        .map(|(entry, end)| (entry.time, end))
+        .collect()
+}
+
+/// The history as far as it is known to have been spent, closing a last entry which does not say
+/// when it was due to switch, as written by older versions, where it starts.
+pub(crate) fn confirmed(history: &[HistoryEntry]) -> Vec<HistoryEntry> {
+    let mut history = history.to_vec();
+    if let Some(last) = history
+        .iter_mut()
+        .rev()
+        .find(|entry| entry.outcome != Outcome::Refused)
+    {
+        last.until = last.until.or(Some(last.time));
+    }
+    history
+}
+
+/// Whether the latest applied entry already records the given profile as due to switch at the
+/// given time, so that applying it again need not be recorded.
+pub(crate) fn is_recorded(
+    history: &[HistoryEntry],
+    to: ProfileType,
+    until: Option<DateTime<Local>>,
+) -> bool {
+    history
+        .iter()
+        .rev()
+        .find(|entry| entry.outcome != Outcome::Refused)
+        .is_some_and(|entry| entry.to == to && entry.until == until)
+}
+
+/// Add an entry to the end of the history file, one JSON object per line.
+pub(crate) fn append(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("failed to create parent directories")?;
+    }
+    let mut history_file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .context(format!("failed to open {}", path.display()))?;
+    writeln!(history_file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// All recorded entries, oldest first.
+pub(crate) fn read(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let Ok(src) = fs::read_to_string(path) else {
+        return Ok(vec![]);
+    };
+    src.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .context(format!("invalid entry {} in {}", index + 1, path.display()))
+                .map_err(Into::into)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn local(day: u32, hour: u32) -> DateTime<Local> {
+        // 2023-08-11 was a Friday.
+        Local
+            .with_ymd_and_hms(2023, 8, day, hour, 0, 0)
+            .single()
+            .unwrap()
+    }
+
+    fn entry(day: u32, hour: u32, to: ProfileType, until: Option<(u32, u32)>) -> HistoryEntry {
+        HistoryEntry::new(
+            local(day, hour),
+            &Transition::new(None, to, Reason::Auto),
+            None,
+        )
+        .until(until.map(|(day, hour)| local(day, hour)))
+    }
+
+    #[test]
+    fn periods() {
+        // Nothing was recorded after work on Friday, as the computer was off.
+        let history = [
+            entry(10, 17, ProfileType::Home, Some((11, 9))),
+            entry(11, 9, ProfileType::Work, Some((11, 17))),
+        ];
+        assert_eq!(
+            vec![(local(11, 9), local(11, 17))],
+            super::periods(&history, ProfileType::Work, &local(14, 9))
+        );
+        assert_eq!(
+            vec![(local(10, 17), local(11, 9))],
+            super::periods(&history, ProfileType::Home, &local(14, 9))
+        );
+        assert_eq!(
+            vec![(local(11, 9), local(11, 12))],
+            super::periods(&history, ProfileType::Work, &local(11, 12))
+        );
+
+        // Entries from before switches were recorded are in force until the next one.
+        let history = [entry(11, 9, ProfileType::Work, None)];
+        assert_eq!(
+            vec![(local(11, 9), local(11, 20))],
+            super::periods(&history, ProfileType::Work, &local(11, 20))
+        );
+    }
+
+    #[test]
+    fn is_recorded() {
+        let history = [
+            entry(11, 9, ProfileType::Work, Some((11, 17))),
+            entry(11, 10, ProfileType::Work, None).outcome(Outcome::Refused),
+        ];
+        let until = Some(local(11, 17));
+        assert!(super::is_recorded(&history, ProfileType::Work, until));
+        assert!(!super::is_recorded(&history, ProfileType::Home, until));
+        assert!(!super::is_recorded(
+            &history,
+            ProfileType::Work,
+            Some(local(11, 19))
+        ));
+        assert!(!super::is_recorded(&[], ProfileType::Work, until));
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize as Deserialise, Serialize as Serialise};
+
+use crate::{
+    error::{ChildProcessExit, Error},
+    process,
+    result::Result,
+    transition::Transition,
+};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const OUTPUT_GRACE_PERIOD: Duration = Duration::from_millis(100);
+
+/// A user command to run when a profile is entered or left.
+#[derive(Clone, Debug, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Hook {
+    command: String,
+
+    /// Seconds to wait for the command before killing it.
+    #[serde(default = "default_timeout")]
+    timeout: u64,
+
+    #[serde(default)]
+    on_failure: FailurePolicy,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+impl Hook {
+    pub(crate) fn command(&self) -> &str {
+        &self.command
+    }
+
+    pub(crate) fn on_failure(&self) -> FailurePolicy {
+        self.on_failure
+    }
+
+    /// Run this hook through the shell, logging its output.
+    pub(crate) fn run(&self, transition: &Transition) -> Result<()> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .envs(transition.env())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // A group of its own, so that any processes it starts can be killed along with it.
+            .process_group(0)
+            .spawn()?;
+
+        let (tx, rx) = mpsc::channel();
+        forward_lines(child.stdout.take(), Output::Stdout, tx.clone());
+        forward_lines(child.stderr.take(), Output::Stderr, tx);
+
+        let timeout = Duration::from_secs(self.timeout);
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break Some(status);
+            }
+            if Instant::now() >= deadline {
+                process::run(
+                    Command::new("kill")
+                        .args(["-KILL", "--"])
+                        .arg(format!("-{}", child.id())),
+                )?;
+                child.wait()?;
+                break None;
+            }
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok((output, line)) => self.log_line(output, &line),
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {}
+            }
+        };
+
+        // Commands which leave background processes behind may never close their output, so only
+        // wait briefly for the last of it.
+        while let Ok((output, line)) = rx.recv_timeout(OUTPUT_GRACE_PERIOD) {
+            self.log_line(output, &line);
+        }
+
+        match status {
+            Some(status) => {
+                log::info!(command = self.command, exit_code = process::exit_code(status); "ran hook")
+            }
+            None => log::info!(command = self.command, timeout = self.timeout; "hook timed out"),
+        }
+        match status {
+            Some(status) if status.success() => Ok(()),
+            Some(status) => Err(Error::ChildProcessError {
+                name: self.command.clone(),
+                reason: status.code().into(),
+            }),
+            None => Err(Error::ChildProcessError {
+                name: self.command.clone(),
+                reason: ChildProcessExit::Timeout(timeout),
+            }),
+        }
+    }
+
+    fn log_line(&self, output: Output, line: &str) {
+        match output {
+            Output::Stdout => log::info!(command = self.command; "{line}"),
+            Output::Stderr => log::warn!(command = self.command; "{line}"),
+        }
+    }
+}
+
+/// Which of a hook's outputs a line came from.
+#[derive(Clone, Copy)]
+enum Output {
+    Stdout,
+    Stderr,
+}
+
+fn forward_lines(
+    output: Option<impl Read + Send + 'static>,
+    kind: Output,
+    tx: Sender<(Output, String)>,
+) {
+    let Some(output) = output else {
+        return;
+    };
+    thread::spawn(move || {
+        for line in BufReader::new(output)
+            .lines()
+            .map_while(std::result::Result::ok)
+        {
+            if tx.send((kind, line)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// What to do when a hook fails.
+#[derive(Copy, Clone, Debug, Default, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum FailurePolicy {
+    /// Carry on silently.
+    Ignore,
+
+    /// Report the failure and carry on.
+    #[default]
+    Warn,
+
+    /// Stop switching profile.
+    Abort,
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use crate::{settings::ProfileType, transition::Reason};
+
+    use super::*;
+
+    fn hook(command: &str, timeout: u64) -> Hook {
+        Hook {
+            command: command.into(),
+            timeout,
+            on_failure: FailurePolicy::default(),
+        }
+    }
+
+    fn transition() -> Transition {
+        Transition::new(Some(ProfileType::Home), ProfileType::Work, Reason::Auto)
+    }
+
+    #[test]
+    fn env() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("env");
+        let command = format!(
+            "echo \"$COMMUTE_OLD_PROFILE $COMMUTE_NEW_PROFILE $COMMUTE_REASON\" > '{}'",
+            out.display()
+        );
+        hook(&command, DEFAULT_TIMEOUT_SECS)
+            .run(&transition())
+            .unwrap();
+        assert_eq!("home work auto\n", fs::read_to_string(out).unwrap());
+    }
+
+    #[test]
+    fn failure() {
+        assert!(matches!(
+            hook("exit 3", DEFAULT_TIMEOUT_SECS).run(&transition()),
+            Err(Error::ChildProcessError {
+                reason: ChildProcessExit::Exit(3),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let command = format!("sleep 10 & echo $! > '{}'; wait", pid_file.display());
+        let start = Instant::now();
+        assert!(matches!(
+            hook(&command, 1).run(&transition()),
+            Err(Error::ChildProcessError {
+                reason: ChildProcessExit::Timeout(_),
+                ..
+            })
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Processes started by the hook are killed with it, once they have been reaped.
+        let pid = fs::read_to_string(pid_file).unwrap();
+        let stat = Path::new("/proc").join(pid.trim()).join("stat");
+        let running = || {
+            fs::read_to_string(&stat).is_ok_and(|stat| {
+                !stat
+                    .rsplit_once(')')
+                    .is_some_and(|(_, fields)| fields.trim_start().starts_with('Z'))
+            })
+        };
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while running() && Instant::now() < deadline {
+            thread::sleep(POLL_INTERVAL);
+        }
+        assert!(!running());
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Write as FmtWrite},
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use log::{
+    kv::{Key, Value, VisitSource},
+    Level, LevelFilter, Log, Metadata, Record,
+};
+use serde::{Deserialize as Deserialise, Serialize as Serialise};
+
+use crate::result::Result;
+
+/// The size past which the log file is rotated when `commute` next starts.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// The number of rotated log files to keep besides the current one.
+const KEPT_LOGS: usize = 3;
+
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A logger which writes to stderr at the chosen verbosity, and everything from debug level up to
+/// the log file.
+struct Logger {
+    stderr_level: LevelFilter,
+    file: Option<Mutex<File>>,
+}
+
+/// One line of the log file.
+#[derive(Debug, Serialise, Deserialise)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct LogEntry {
+    time: DateTime<Local>,
+    level: Level,
+    target: String,
+    message: String,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    fields: BTreeMap<String, String>,
+}
+
+impl LogEntry {
+    fn new(record: &Record) -> Self {
+        let mut fields = Fields::default();
+        let _ = record.key_values().visit(&mut fields);
+        Self {
+            time: Local::now(),
+            level: record.level(),
+            target: record.target().into(),
+            message: record.args().to_string(),
+            fields: fields.0,
+        }
+    }
+}
+
+impl Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:<5} {}{}",
+            self.time.format("%Y-%m-%d %H:%M:%S"),
+            self.level,
+            self.message,
+            format_fields(&self.fields)
+        )
+    }
+}
+
+/// The key-value pairs attached to a record, written after its message.
+#[derive(Default)]
+struct Fields(BTreeMap<String, String>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(
+        &mut self,
+        key: Key<'kvs>,
+        value: Value<'kvs>,
+    ) -> std::result::Result<(), log::kv::Error> {
+        self.0.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+/// Write key-value pairs as ` key=value`, quoting values which would otherwise be ambiguous.
+fn format_fields(fields: &BTreeMap<String, String>) -> String {
+    let mut formatted = String::new();
+    for (key, value) in fields {
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            let _ = write!(formatted, " {key}={value:?}");
+        } else {
+            let _ = write!(formatted, " {key}={value}");
+        }
+    }
+    formatted
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target().starts_with(env!("CARGO_PKG_NAME"))
+            && (metadata.level() <= self.stderr_level
+                || (self.file.is_some() && metadata.level() <= Level::Debug))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let entry = LogEntry::new(record);
+        if record.level() <= self.stderr_level {
+            let level = match entry.level {
+                Level::Warn => "warning".into(),
+                level => level.as_str().to_lowercase(),
+            };
+            eprintln!("{level}: {}{}", entry.message, format_fields(&entry.fields));
+        }
+
+        let Some(file) = &self.file else {
+            return;
+        };
+        if record.level() > Level::Debug {
+            return;
+        }
+        if let (Ok(mut file), Ok(line)) = (file.lock(), serde_json::to_string(&entry)) {
+            let _ = writeln!(file, "{line}");
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(Ok(mut file)) = self.file.as_ref().map(Mutex::lock) {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Start logging to the given file, with the stderr level raised by each `-v` and lowered by each
+/// `-q`.
+pub(crate) fn init(verbosity: i8, path: &Path) {
+    let stderr_level = match verbosity {
+        i8::MIN..=-2 => LevelFilter::Off,
+        -1 => LevelFilter::Error,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        3.. => LevelFilter::Trace,
+    };
+    let file = match open(path) {
+        Ok(file) => Some(Mutex::new(file)),
+        Err(e) => {
+            if stderr_level >= LevelFilter::Warn {
+                eprintln!("warning: not logging to a file: {e}");
+            }
+            None
+        }
+    };
+    let max_level = if file.is_some() {
+        stderr_level.max(LevelFilter::Debug)
+    } else {
+        stderr_level
+    };
+    if log::set_boxed_logger(Box::new(Logger { stderr_level, file })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Open the log file for appending, first rotating it if it has grown too large.
+fn open(path: &Path) -> Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("failed to create parent directories")?;
+    }
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() >= MAX_LOG_SIZE) {
+        for index in (1..KEPT_LOGS).rev() {
+            let _ = fs::rename(rotated_path(path, index), rotated_path(path, index + 1));
+        }
+        fs::rename(path, rotated_path(path, 1))
+            .context(format!("failed to rotate {}", path.display()))?;
+    }
+    Ok(OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .context(format!("failed to open {}", path.display()))?)
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    path.with_extension(format!("log.{index}"))
+}
+
+/// Print the most recent entries of the log file, then if `follow` is set, print new entries as
+/// they are written.
+pub(crate) fn show(path: &Path, lines: usize, follow: bool) -> Result<()> {
+    let (recent, mut position) = read_lines(path, 0)?;
+    for line in &recent[recent.len().saturating_sub(lines)..] {
+        print_line(line);
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    loop {
+        thread::sleep(FOLLOW_INTERVAL);
+        let (new, end) = read_lines(path, position)?;
+        for line in &new {
+            print_line(line);
+        }
+        position = end;
+    }
+}
+
+/// Read the complete lines of the log file from the given position, returning them with the
+/// position to read from next. A partly written line is left for the next read.
+fn read_lines(path: &Path, position: u64) -> Result<(Vec<String>, u64)> {
+    let Ok(mut file) = File::open(path) else {
+        return Ok((vec![], position));
+    };
+    // A file shorter than what has been read is a new one, the old one having been rotated.
+    let position = if file.metadata()?.len() < position {
+        0
+    } else {
+        position
+    };
+    file.seek(SeekFrom::Start(position))?;
+    let mut new = String::new();
+    file.read_to_string(&mut new)?;
+    let complete = new.rfind('\n').map_or(0, |end| end + 1);
+    Ok((
+        new[..complete].lines().map(String::from).collect(),
+        position + complete as u64,
+    ))
+}
+
+fn print_line(line: &str) {
+    match serde_json::from_str::<LogEntry>(line) {
+        Ok(entry) => println!("{entry}"),
+        Err(_) => println!("{line}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("commute.log");
+        for index in 1..=KEPT_LOGS {
+            fs::write(rotated_path(&path, index), index.to_string()).unwrap();
+        }
+
+        fs::write(&path, "small").unwrap();
+        drop(open(&path).unwrap());
+        assert_eq!("small", fs::read_to_string(&path).unwrap());
+
+        fs::write(&path, vec![b'x'; MAX_LOG_SIZE as usize]).unwrap();
+        writeln!(open(&path).unwrap(), "new").unwrap();
+        assert_eq!("new\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(
+            MAX_LOG_SIZE,
+            fs::metadata(rotated_path(&path, 1)).unwrap().len()
+        );
+        for index in 2..=KEPT_LOGS {
+            assert_eq!(
+                (index - 1).to_string(),
+                fs::read_to_string(rotated_path(&path, index)).unwrap()
+            );
+        }
+        assert!(!rotated_path(&path, KEPT_LOGS + 1).exists());
+    }
+
+    #[test]
+    fn read_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("commute.log");
+        assert_eq!((vec![], 0), super::read_lines(&path, 0).unwrap());
+
+        fs::write(&path, "one\ntwo\nthr").unwrap();
+        let (lines, position) = super::read_lines(&path, 0).unwrap();
+        assert_eq!(
+            (vec!["one".to_string(), "two".into()], 8),
+            (lines, position)
+        );
+
+        fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        assert_eq!(
+            (vec!["three".to_string()], 14),
+            super::read_lines(&path, position).unwrap()
+        );
+
+        // The log was rotated.
+        fs::write(&path, "four\n").unwrap();
+        assert_eq!(
+            (vec!["four".to_string()], 5),
+            super::read_lines(&path, 14).unwrap()
+        );
+    }
+
+    #[test]
+    fn format_fields() {
+        let fields = BTreeMap::from([
+            ("key".to_string(), "picture-uri".to_string()),
+            ("value".to_string(), "a b".to_string()),
+        ]);
+        assert_eq!(
+            r#" key=picture-uri value="a b""#,
+            super::format_fields(&fields)
+        );
+    }
+}
@@ -1,54 +1,150 @@
+mod action;
+mod app;
+// The tests of the arguments predate the lint against `.err().expect()`.
+#[cfg_attr(test, allow(clippy::err_expect))]
 mod args;
+mod browser;
+mod calendar;
+mod cap;
 mod configurator;
+mod desktop;
+mod environment;
 mod error;
+mod git;
+mod history;
+mod hook;
+mod logger;
+mod migration;
+mod notifier;
+mod overtime;
+mod process;
 mod profile_applicator;
+mod reminder;
+mod report;
 mod result;
+mod schedule;
 mod settings;
+mod transition;
+mod xdg;
 
 use std::process::ExitCode;
 
 use anyhow::Context;
-use args::{Config, ConfigKey, ProfileConfig, WorkHoursConfig};
-use chrono::Duration;
+use args::{
+    Config, ConfigKey, DesktopConfig, LogArgs, OvertimeArgs, ProfileConfig, ReportArgs,
+    ReportFormat, Schedule, WorkHoursConfig,
+};
+use chrono::{Datelike, Duration, Local};
 use clap::Parser;
 use lazy_static::lazy_static;
 use settings::Override;
 
 use crate::args::{Args, Command};
 use crate::configurator::Configurator;
-use crate::profile_applicator::ProfileApplicator;
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::git::GitIdentity;
+use crate::history::{HistoryEntry, Outcome};
+use crate::notifier::{Notification, Notifier};
+use crate::profile_applicator::{Applied, ProfileApplicator};
 use crate::result::Result;
-use crate::settings::{ProfileType, Settings};
+use crate::settings::{ProfileType, Settings, SettingsPaths};
+use crate::transition::{Reason, Transition};
 
 lazy_static! {
     static ref DAY_OVERRIDE_DURATION: Duration = Duration::hours(12);
+    static ref STAY_DURATION: Duration = Duration::hours(1);
 }
 
 fn main() -> ExitCode {
     let args = Args::parse();
-    if let Err(e) = run(args) {
-        eprintln!("{e}");
-        return ExitCode::FAILURE;
+    let paths = SettingsPaths::new(args.config_path());
+    logger::init(args.verbosity(), paths.log());
+    if let Err(e) = run(args, paths) {
+        log::error!("{e}");
+        return e.exit_code();
     }
     ExitCode::SUCCESS
 }
 
-fn run(args: Args) -> Result<()> {
-    let mut settings = Settings::new().context("failed to read settings")?;
+fn run(args: Args, paths: SettingsPaths) -> Result<()> {
+    let mut settings = Settings::new(paths).context("failed to read settings")?;
 
-    match args.command().unwrap_or(&Default::default()) {
-        Command::Auto => ProfileApplicator::auto(&settings).apply(),
+    let default_command = Command::default();
+    let command = args.command().unwrap_or(&default_command);
+    let mut outcome = Outcome::Applied;
+    let (applied, applying) = match command {
+        Command::Auto => apply(ProfileApplicator::auto(&settings)?, &args),
         Command::Norm => {
             settings.reset_override();
-            ProfileApplicator::auto(&settings).apply()
+            apply(ProfileApplicator::auto(&settings)?, &args)
         }
-        Command::Work { input_duration } => {
-            settings.set_override(Override::new(ProfileType::Work, input_duration.duration()));
-            ProfileApplicator::new(&settings, ProfileType::Work).apply()
+        Command::Work {
+            input_duration,
+            force,
+        } => {
+            if let Some(cap) = settings
+                .work_time_cap()
+                .reached(&history::read(settings.paths().history())?, &Local::now())
+            {
+                log::info!(cap:% = cap, force = *force; "work-time cap reached");
+                if !force {
+                    let transition =
+                        Transition::new(settings.applied(), ProfileType::Work, Reason::Manual);
+                    if !args.dry_run() {
+                        history::append(
+                            settings.paths().history(),
+                            &HistoryEntry::new(Local::now(), &transition, args.note())
+                                .outcome(Outcome::Refused),
+                        )?;
+                    }
+                    return Err(Error::WorkTimeCapError { cap });
+                }
+                outcome = Outcome::Forced;
+            }
+            settings.set_override(
+                Override::new(ProfileType::Work, input_duration.duration())
+                    .forced(outcome == Outcome::Forced),
+            );
+            apply(ProfileApplicator::new(&settings, ProfileType::Work), &args)
         }
         Command::Home { input_duration } => {
             settings.set_override(Override::new(ProfileType::Home, input_duration.duration()));
-            ProfileApplicator::new(&settings, ProfileType::Home).apply()
+            apply(ProfileApplicator::new(&settings, ProfileType::Home), &args)
+        }
+        Command::Browsers => {
+            for browser in browser::installed_browsers()? {
+                println!("{browser}");
+            }
+            (None, Ok(()))
+        }
+        Command::Env => {
+            print_env(&settings)?;
+            (None, Ok(()))
+        }
+        Command::Report(report_args) => {
+            print_report(&settings, report_args)?;
+            (None, Ok(()))
+        }
+        Command::Overtime(overtime_args) => {
+            print_overtime(&mut settings, overtime_args)?;
+            (None, Ok(()))
+        }
+        Command::Schedule(schedule) => {
+            match &schedule.schedule {
+                Schedule::Export => {
+                    print!("{}", calendar::ics(&settings, &Local::now()));
+                }
+            }
+            (None, Ok(()))
+        }
+        Command::Log(LogArgs { lines, follow }) => {
+            logger::show(settings.paths().log(), *lines, *follow)?;
+            (None, Ok(()))
+        }
+        Command::Status => {
+            print_status(&settings)?;
+            (None, Ok(()))
         }
         Command::Config(config) => {
             let mut configurator = Configurator::new(&mut settings);
@@ -81,14 +177,235 @@ fn run(args: Args) -> Result<()> {
                         _ => print!("{}", serde_yaml::to_string(configurator.clocking_times())?),
                     }
                 }
-                None => print!("{}", serde_yaml::to_string(configurator.settings())?),
+                Some(Config::Desktop(DesktopConfig { desktop })) => match desktop {
+                    Some(desktop) => configurator.set_desktop(desktop)?,
+                    None => println!("{}", configurator.desktop()),
+                },
+                Some(Config::Path) => {
+                    let paths = configurator.paths();
+                    println!("config: {}", paths.config().display());
+                    println!("state: {}", paths.state().display());
+                    println!("history: {}", paths.history().display());
+                    println!("log: {}", paths.log().display());
+                }
+                None => print!("{}", serde_yaml::to_string(configurator.config())?),
             }
-            Ok(())
+            (None, Ok(()))
+        }
+    };
+
+    // Remember whatever was applied before reporting any failure.
+    if let Some(applied) = &applied {
+        record(&mut settings, applied, &args, outcome)?;
+    }
+
+    if !args.dry_run() {
+        settings.save()?;
+    }
+    applying?;
+
+    if let Command::Auto = command {
+        reminder::send_due(&mut settings, &Local::now(), args.dry_run())?;
+        if !args.dry_run() {
+            settings.save()?;
         }
-    }?;
+    }
 
-    settings.save()?;
+    let Some(transition) = applied.map(|applied| *applied.transition()) else {
+        return Ok(());
+    };
+    if args.dry_run()
+        || !transition.is_change()
+        || transition.reason() == Reason::Manual
+        || !settings.switch_notification().enabled()
+    {
+        return Ok(());
+    }
+    if let Some(profile_type) = notify_switch(&settings, &transition) {
+        log::info!(profile:% = profile_type; "user chose to stay");
+        settings.set_override(Override::new(profile_type, *STAY_DURATION));
+        let (applied, applying) = apply(ProfileApplicator::auto(&settings)?, &args);
+        if let Some(applied) = &applied {
+            record(&mut settings, applied, &args, Outcome::Applied)?;
+        }
+        settings.save()?;
+        applying?;
+    }
+
+    Ok(())
+}
 
+/// Apply a profile, returning what was applied alongside any failure so that a partly applied
+/// profile is still recorded.
+fn apply(applicator: ProfileApplicator, args: &Args) -> (Option<Applied>, Result<()>) {
+    let applicator = applicator
+        .dry_run(args.dry_run())
+        .rollback_on_error(args.rollback_on_error())
+        .best_effort(args.best_effort());
+    let (applied, result) = applicator.apply();
+    (Some(applied), result)
+}
+
+/// Note that a profile was applied, adding it to the history if the profile changed, the
+/// work-time cap was overridden or the profile is now due to switch at another time.
+fn record(settings: &mut Settings, applied: &Applied, args: &Args, outcome: Outcome) -> Result<()> {
+    settings.set_launched_apps(applied.launched_apps().to_vec());
+    if applied.git_include_installed() {
+        settings.set_git_include_installed();
+    }
+    if !applied.entered() {
+        return Ok(());
+    }
+    let transition = applied.transition();
+    settings.set_applied(transition.to());
+    if args.dry_run() {
+        return Ok(());
+    }
+    let now = Local::now();
+    let until = schedule::next_switch(settings, &now)
+        .map(|(time, _)| time)
+        .or_else(|| {
+            settings
+                .r#override()
+                .filter(|r#override| r#override.advise_profile().is_some())
+                .map(Override::until)
+        });
+    if !transition.is_change()
+        && outcome == Outcome::Applied
+        && history::is_recorded(
+            &history::read(settings.paths().history())?,
+            transition.to(),
+            until,
+        )
+    {
+        return Ok(());
+    }
+    log::debug!(to:% = transition.to(), outcome:? = outcome; "recording history entry");
+    history::append(
+        settings.paths().history(),
+        &HistoryEntry::new(now, transition, args.note())
+            .outcome(outcome)
+            .until(until),
+    )
+}
+
+fn print_report(settings: &Settings, report_args: &ReportArgs) -> Result<()> {
+    let now = Local::now();
+    let today = now.date_naive();
+    let (from, to) = match (report_args.from, report_args.to) {
+        (Some(from), Some(to)) => (from, to),
+        _ if report_args.month => (today.with_day(1).unwrap_or(today), today),
+        _ => (
+            today - Duration::days(today.weekday().num_days_from_monday().into()),
+            today,
+        ),
+    };
+    let days = report::daily_totals(&history::read(settings.paths().history())?, from, to, &now);
+    match report_args.format {
+        ReportFormat::Table => print!("{}", report::table(&days)),
+        ReportFormat::Csv => print!("{}", report::csv(&days)),
+        ReportFormat::Json => print!("{}", report::json(&days)?),
+    }
+    Ok(())
+}
+
+fn print_overtime(settings: &mut Settings, overtime_args: &OvertimeArgs) -> Result<()> {
+    if let Some(reset) = overtime_args.reset {
+        settings.set_flexi_reset(reset);
+    }
+    let history = history::read(settings.paths().history())?;
+    let Some(first) = history.first() else {
+        println!("no history yet");
+        return Ok(());
+    };
+    let since = settings
+        .flexi_reset()
+        .unwrap_or_else(|| first.time().date_naive());
+    let weeks = overtime::weekly(&history, settings, since, &Local::now());
+    let balance = weeks
+        .iter()
+        .fold(Duration::zero(), |balance, week| balance + week.balance());
+    let shown = &weeks[weeks.len().saturating_sub(overtime_args.weeks)..];
+    print!("{}", overtime::table(shown, balance, since));
+    Ok(())
+}
+
+/// Tell the user that a profile was applied without their asking, returning the profile they chose
+/// to stay in instead, if any.
+fn notify_switch(settings: &Settings, transition: &Transition) -> Option<ProfileType> {
+    let why = match transition.reason() {
+        Reason::Override => "an override is in force",
+        Reason::Cap => "the work-time cap has been reached",
+        _ => "of the work hours",
+    };
+    let mut body = format!("Applied because {why}.");
+    match schedule::next_switch(settings, &Local::now()) {
+        Some((time, profile_type)) => {
+            body += &format!(
+                " Next switch: {profile_type} at {}.",
+                time.format("%a %H:%M")
+            )
+        }
+        None => body += " No switch is scheduled.",
+    }
+
+    let mut notification = Notification::new(format!("Switched to {}", transition.to()), body);
+    // Staying past the work-time cap needs --force, so is not offered.
+    if transition.from().is_some() && transition.reason() != Reason::Cap {
+        notification = notification.action("stay", "Stay 1 more hour");
+    }
+
+    let action = Notifier::session().and_then(|notifier| {
+        notifier.notify(
+            &notification,
+            settings.switch_notification().action_timeout(),
+        )
+    });
+    match action {
+        Ok(Some(action)) if action == "stay" => transition.from(),
+        Ok(_) => None,
+        Err(e) => {
+            log::warn!("failed to send notification: {e}");
+            None
+        }
+    }
+}
+
+/// Print shell commands which switch to the variables of the active profile.
+fn print_env(settings: &Settings) -> Result<()> {
+    let profile_type = match settings.applied() {
+        Some(applied) => applied,
+        None => ProfileApplicator::auto(settings)?.transition().to(),
+    };
+    let others: Vec<&str> = [ProfileType::Work, ProfileType::Home]
+        .into_iter()
+        .flat_map(|profile_type| settings.profile(profile_type).env().keys())
+        .map(String::as_str)
+        .collect();
+    let environment = Environment::from(settings.profile(profile_type).env().clone());
+    print!("{}", environment.exports(&others));
+    Ok(())
+}
+
+fn print_status(settings: &Settings) -> Result<()> {
+    match settings.applied() {
+        Some(applied) => println!("profile: {applied}"),
+        None => println!("profile: none"),
+    }
+    if let Some(r#override) = settings
+        .r#override()
+        .filter(|o| o.advise_profile().is_some())
+    {
+        println!(
+            "override: {} until {}",
+            r#override.profile_type(),
+            r#override.until().format("%Y-%m-%d %H:%M")
+        );
+    }
+    match GitIdentity::current()? {
+        Some(identity) => println!("git: {identity}"),
+        None => println!("git: not managed"),
+    }
     Ok(())
 }
 
@@ -99,7 +416,7 @@ fn handle_profile_config(
     value: Option<&str>,
 ) -> Result<()> {
     match (key, value) {
-        (Some(key), Some(value)) => configurator.set(profile_type, key, value),
+        (Some(key), Some(value)) => configurator.set(profile_type, key, value)?,
         (Some(key), _) => println!("{}", configurator.get(profile_type, key).unwrap_or("none")),
         _ => print!(
             "{}",
@@ -0,0 +1,141 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use serde_yaml::{Mapping, Value};
+
+use crate::{error::Error, result::Result};
+
+/// The version of the config format which this build reads and writes.
+pub(crate) const SETTINGS_VERSION: u64 = 2;
+
+/// The version of the state format which this build reads and writes.
+pub(crate) const STATE_VERSION: u64 = 1;
+
+/// The version of files written before they recorded one.
+const UNVERSIONED: u64 = 1;
+
+/// A step which upgrades a file by one version, given somewhere to move keys which now belong in
+/// the state file.
+type Migration = fn(&mut Mapping, &mut Mapping);
+
+/// The steps which upgrade a config by one version each, the first from version 1 to 2.
+const CONFIG_MIGRATIONS: [Migration; (SETTINGS_VERSION - UNVERSIONED) as usize] = [move_state];
+
+/// The steps which upgrade a state file by one version each.
+const STATE_MIGRATIONS: [Migration; (STATE_VERSION - UNVERSIONED) as usize] = [];
+
+/// Upgrade a config to the current version step by step, returning the version it had. Any state
+/// found in it is moved to `state`.
+pub(crate) fn migrate_config(path: &Path, config: &mut Value, state: &mut Mapping) -> Result<u64> {
+    migrate(path, config, SETTINGS_VERSION, &CONFIG_MIGRATIONS, state)
+}
+
+/// Upgrade a state file to the current version step by step, returning the version it had.
+pub(crate) fn migrate_state(path: &Path, state: &mut Value) -> Result<u64> {
+    migrate(
+        path,
+        state,
+        STATE_VERSION,
+        &STATE_MIGRATIONS,
+        &mut Mapping::new(),
+    )
+}
+
+fn migrate(
+    path: &Path,
+    value: &mut Value,
+    current: u64,
+    migrations: &[Migration],
+    state: &mut Mapping,
+) -> Result<u64> {
+    if value.is_null() {
+        *value = Value::Mapping(Mapping::new());
+    }
+    let Some(mapping) = value.as_mapping_mut() else {
+        return Ok(current);
+    };
+
+    let version = match mapping.get("version") {
+        None => UNVERSIONED,
+        Some(version) => version
+            .as_u64()
+            .filter(|version| *version >= UNVERSIONED)
+            .ok_or_else(|| anyhow!("invalid settings version in {}", path.display()))?,
+    };
+    if version > current {
+        return Err(Error::SettingsVersionError {
+            path: path.into(),
+            version,
+            supported: current,
+        });
+    }
+
+    for (from, migration) in (version..).zip(&migrations[(version - UNVERSIONED) as usize..]) {
+        migration(mapping, state);
+        log::info!(path:% = path.display(), from, to = from + 1; "upgraded settings");
+    }
+    mapping.insert("version".into(), current.into());
+    Ok(version)
+}
+
+/// Version 2 keeps the state which `commute` records between runs in a file of its own, so move
+/// it out of configs copied from the old combined settings file.
+fn move_state(config: &mut Mapping, state: &mut Mapping) {
+    for key in ["override", "applied", "sent-reminders", "flexi-reset"] {
+        if let Some(value) = config.remove(key) {
+            state.insert(key.into(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn migrate() {
+        let path = Path::new("config.yml");
+        let mut config: Value =
+            serde_yaml::from_str("applied: work\nwork-hours: {clock-on: '09:00:00'}\n").unwrap();
+        let mut state = Mapping::new();
+        assert_eq!(1, migrate_config(path, &mut config, &mut state).unwrap());
+        assert_eq!(
+            serde_yaml::from_str::<Value>("work-hours: {clock-on: '09:00:00'}\nversion: 2\n")
+                .unwrap(),
+            config
+        );
+        assert_eq!(
+            serde_yaml::from_str::<Mapping>("applied: work\n").unwrap(),
+            state
+        );
+
+        assert_eq!(2, migrate_config(path, &mut config, &mut state).unwrap());
+
+        let mut config: Value = serde_yaml::from_str("version: 3\n").unwrap();
+        assert!(matches!(
+            migrate_config(path, &mut config, &mut state),
+            Err(Error::SettingsVersionError { version: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn migrate_state() {
+        let path = Path::new("state.yml");
+        let mut state: Value = serde_yaml::from_str("applied: work\n").unwrap();
+        assert_eq!(1, super::migrate_state(path, &mut state).unwrap());
+        assert_eq!(
+            serde_yaml::from_str::<Value>("applied: work\nversion: 1\n").unwrap(),
+            state
+        );
+
+        let mut state: Value = serde_yaml::from_str("version: 2\n").unwrap();
+        assert!(matches!(
+            super::migrate_state(path, &mut state),
+            Err(Error::SettingsVersionError {
+                version: 2,
+                supported: 1,
+                ..
+            })
+        ));
+    }
+}
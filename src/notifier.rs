@@ -0,0 +1,361 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use gio::glib::{self, ToVariant, Variant, VariantTy};
+
+use crate::result::Result;
+
+const BUS_NAME: &str = "org.freedesktop.Notifications";
+const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+const CALL_TIMEOUT_MS: i32 = 5000;
+
+/// A notification to show on the desktop.
+#[derive(Debug)]
+pub(crate) struct Notification {
+    summary: String,
+    body: String,
+    actions: Vec<(String, String)>,
+}
+
+impl Notification {
+    pub(crate) fn new(summary: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            summary: summary.into(),
+            body: body.into(),
+            actions: vec![],
+        }
+    }
+
+    /// Offer a button with the given label, which is reported by its key when chosen.
+    pub(crate) fn action(mut self, key: impl Into<String>, label: impl Into<String>) -> Self {
+        self.actions.push((key.into(), label.into()));
+        self
+    }
+}
+
+/// Sends notifications through a server implementing `org.freedesktop.Notifications`.
+pub(crate) struct Notifier {
+    connection: gio::DBusConnection,
+}
+
+impl Notifier {
+    /// Connect to the notification server on the session bus.
+    pub(crate) fn session() -> Result<Self> {
+        Ok(Self::new(gio::bus_get_sync(
+            gio::BusType::Session,
+            None::<&gio::Cancellable>,
+        )?))
+    }
+
+    pub(crate) fn new(connection: gio::DBusConnection) -> Self {
+        Self { connection }
+    }
+
+    /// Show a notification, then wait up to the timeout for one of its actions to be chosen,
+    /// returning that action's key.
+    pub(crate) fn notify(
+        &self,
+        notification: &Notification,
+        timeout: Duration,
+    ) -> Result<Option<String>> {
+        // Signals are delivered to the thread-default context when they are subscribed to.
+        let context = glib::MainContext::new();
+        context.with_thread_default(|| self.notify_on(&context, notification, timeout))?
+    }
+
+    fn notify_on(
+        &self,
+        context: &glib::MainContext,
+        notification: &Notification,
+        timeout: Duration,
+    ) -> Result<Option<String>> {
+        let signals = Rc::new(RefCell::new(vec![]));
+        let subscription = {
+            let signals = signals.clone();
+            self.connection.signal_subscribe(
+                Some(BUS_NAME),
+                Some(INTERFACE),
+                None,
+                Some(OBJECT_PATH),
+                None,
+                gio::DBusSignalFlags::NONE,
+                move |_, _, _, _, name, parameters| {
+                    if let Some(signal) = Signal::parse(name, parameters) {
+                        signals.borrow_mut().push(signal);
+                    }
+                },
+            )
+        };
+        let result = self.show(notification).and_then(|id| {
+            if notification.actions.is_empty() || !self.supports_actions()? {
+                return Ok(None);
+            }
+            Ok(wait_for_action(context, &signals, id, timeout))
+        });
+        self.connection.signal_unsubscribe(subscription);
+        result
+    }
+
+    fn show(&self, notification: &Notification) -> Result<u32> {
+        let actions: Vec<&str> = notification
+            .actions
+            .iter()
+            .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+            .collect();
+        let hints: HashMap<&str, Variant> = HashMap::new();
+        let reply = self.call(
+            "Notify",
+            Some(
+                &(
+                    env!("CARGO_PKG_NAME"),
+                    0u32,
+                    "",
+                    &notification.summary,
+                    &notification.body,
+                    actions,
+                    hints,
+                    -1i32,
+                )
+                    .to_variant(),
+            ),
+            "(u)",
+        )?;
+        let (id,) = reply
+            .get::<(u32,)>()
+            .ok_or_else(|| anyhow::anyhow!("unexpected reply to Notify: {reply}"))?;
+        Ok(id)
+    }
+
+    fn supports_actions(&self) -> Result<bool> {
+        let reply = self.call("GetCapabilities", None, "(as)")?;
+        let (capabilities,) = reply
+            .get::<(Vec<String>,)>()
+            .ok_or_else(|| anyhow::anyhow!("unexpected reply to GetCapabilities: {reply}"))?;
+        Ok(capabilities
+            .iter()
+            .any(|capability| capability == "actions"))
+    }
+
+    fn call(
+        &self,
+        method: &str,
+        parameters: Option<&Variant>,
+        reply_type: &str,
+    ) -> Result<Variant> {
+        Ok(self.connection.call_sync(
+            Some(BUS_NAME),
+            OBJECT_PATH,
+            INTERFACE,
+            method,
+            parameters,
+            Some(VariantTy::new(reply_type).expect("invalid reply type")),
+            gio::DBusCallFlags::NONE,
+            CALL_TIMEOUT_MS,
+            None::<&gio::Cancellable>,
+        )?)
+    }
+}
+
+/// Run the context until the notification with the given ID is acted on, is closed or the timeout
+/// passes.
+fn wait_for_action(
+    context: &glib::MainContext,
+    signals: &RefCell<Vec<Signal>>,
+    id: u32,
+    timeout: Duration,
+) -> Option<String> {
+    let deadline = Instant::now() + timeout;
+    // Wake the context at the deadline, in case no signal does so first.
+    let alarm = glib::timeout_source_new(timeout, None, glib::PRIORITY_DEFAULT, || {
+        glib::Continue(false)
+    });
+    alarm.attach(Some(context));
+
+    let action = loop {
+        let outcome = signals.borrow().iter().find_map(|signal| match signal {
+            Signal::ActionInvoked { id: signal_id, key } if *signal_id == id => {
+                Some(Some(key.clone()))
+            }
+            Signal::Closed { id: signal_id } if *signal_id == id => Some(None),
+            _ => None,
+        });
+        if let Some(outcome) = outcome {
+            break outcome;
+        }
+        if Instant::now() >= deadline {
+            break None;
+        }
+        context.iteration(true);
+    };
+    alarm.destroy();
+    action
+}
+
+/// A signal sent by the notification server about one of its notifications.
+#[derive(Debug)]
+enum Signal {
+    ActionInvoked { id: u32, key: String },
+    Closed { id: u32 },
+}
+
+impl Signal {
+    fn parse(name: &str, parameters: &Variant) -> Option<Self> {
+        match name {
+            "ActionInvoked" => {
+                let (id, key) = parameters.get::<(u32, String)>()?;
+                Some(Self::ActionInvoked { id, key })
+            }
+            "NotificationClosed" => {
+                let (id, _reason) = parameters.get::<(u32, u32)>()?;
+                Some(Self::Closed { id })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Command, Stdio},
+        sync::{mpsc, Arc, Mutex},
+        thread,
+    };
+
+    use super::*;
+    use crate::process;
+
+    const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.freedesktop.Notifications">
+    <method name="GetCapabilities">
+      <arg direction="out" type="as"/>
+    </method>
+    <method name="Notify">
+      <arg direction="in" type="s"/>
+      <arg direction="in" type="u"/>
+      <arg direction="in" type="s"/>
+      <arg direction="in" type="s"/>
+      <arg direction="in" type="s"/>
+      <arg direction="in" type="as"/>
+      <arg direction="in" type="a{sv}"/>
+      <arg direction="in" type="i"/>
+      <arg direction="out" type="u"/>
+    </method>
+  </interface>
+</node>"#;
+
+    fn connect(address: &str) -> gio::DBusConnection {
+        gio::DBusConnection::for_address_sync(
+            address,
+            gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+                | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+            None,
+            None::<&gio::Cancellable>,
+        )
+        .expect("failed to connect to private bus")
+    }
+
+    /// Serve `org.freedesktop.Notifications` on the given bus, choosing the first action of every
+    /// notification and recording its summary.
+    fn serve_stand_in(address: String, summaries: Arc<Mutex<Vec<String>>>) {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let context = glib::MainContext::new();
+            context
+                .with_thread_default(|| {
+                    let connection = connect(&address);
+                    connection
+                        .call_sync(
+                            Some("org.freedesktop.DBus"),
+                            "/org/freedesktop/DBus",
+                            "org.freedesktop.DBus",
+                            "RequestName",
+                            Some(&(BUS_NAME, 0u32).to_variant()),
+                            None,
+                            gio::DBusCallFlags::NONE,
+                            CALL_TIMEOUT_MS,
+                            None::<&gio::Cancellable>,
+                        )
+                        .expect("failed to own name");
+                    let node = gio::DBusNodeInfo::for_xml(INTERFACE_XML).unwrap();
+                    connection
+                        .register_object(
+                            OBJECT_PATH,
+                            &node.lookup_interface(INTERFACE).unwrap(),
+                            move |connection, _, _, _, method, parameters, invocation| match method
+                            {
+                                "GetCapabilities" => {
+                                    invocation.return_value(Some(&(vec!["actions"],).to_variant()))
+                                }
+                                "Notify" => {
+                                    let summary = parameters.child_value(3).get::<String>();
+                                    summaries.lock().unwrap().extend(summary);
+                                    let actions = parameters.child_value(5).get::<Vec<String>>();
+                                    invocation.return_value(Some(&(7u32,).to_variant()));
+                                    if let Some(key) = actions.unwrap_or_default().first() {
+                                        connection
+                                            .emit_signal(
+                                                None,
+                                                OBJECT_PATH,
+                                                INTERFACE,
+                                                "ActionInvoked",
+                                                Some(&(7u32, key).to_variant()),
+                                            )
+                                            .unwrap();
+                                    }
+                                }
+                                _ => unreachable!(),
+                            },
+                            |_, _, _, _, _| unreachable!(),
+                            |_, _, _, _, _, _| false,
+                        )
+                        .unwrap();
+                    ready_tx.send(()).unwrap();
+                    loop {
+                        context.iteration(true);
+                    }
+                })
+                .unwrap();
+        });
+        ready_rx.recv().unwrap();
+    }
+
+    #[test]
+    fn notify() {
+        if !process::in_path("dbus-daemon") {
+            eprintln!("skipping: dbus-daemon not installed");
+            return;
+        }
+        let mut bus = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut address = String::new();
+        BufReader::new(bus.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let address = address.trim().to_string();
+
+        let summaries = Arc::new(Mutex::new(vec![]));
+        serve_stand_in(address.clone(), summaries.clone());
+
+        let notifier = Notifier::new(connect(&address));
+        let action = notifier.notify(
+            &Notification::new("Switched to work", "").action("stay", "Stay 1 more hour"),
+            Duration::from_secs(5),
+        );
+        bus.kill().unwrap();
+        bus.wait().unwrap();
+
+        assert_eq!(Some("stay".to_string()), action.unwrap());
+        assert_eq!(vec!["Switched to work"], *summaries.lock().unwrap());
+    }
+}
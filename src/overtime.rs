@@ -0,0 +1,204 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+
+use crate::{
+    history::{self, HistoryEntry},
+    report::{hours_minutes, start_of_day},
+    schedule,
+    settings::{ProfileType, Settings},
+};
+
+/// Time worked in one week compared with the work hours.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct WeekOvertime {
+    /// The Monday which starts the week.
+    start: NaiveDate,
+    scheduled: Duration,
+    worked: Duration,
+
+    /// Time in the work profile outside the work hours.
+    extra: Duration,
+
+    /// Time in the work hours spent outside the work profile.
+    missing: Duration,
+}
+
+impl WeekOvertime {
+    fn new(start: NaiveDate) -> Self {
+        Self {
+            start,
+            scheduled: Duration::zero(),
+            worked: Duration::zero(),
+            extra: Duration::zero(),
+            missing: Duration::zero(),
+        }
+    }
+
+    /// The time by which the flexi-time balance changed over this week.
+    pub(crate) fn balance(&self) -> Duration {
+        self.extra - self.missing
+    }
+}
+
+/// Compare the time spent in the work profile with the work hours for each week containing a day
+/// from `from` onwards, not counting leave days as work hours. Only time since the history began
+/// and before `now` is counted.
+pub(crate) fn weekly(
+    history: &[HistoryEntry],
+    settings: &Settings,
+    from: NaiveDate,
+    now: &DateTime<Local>,
+) -> Vec<WeekOvertime> {
+    let Some(first) = history.first() else {
+        return vec![];
+    };
+    let periods = history::periods(history, ProfileType::Work, now);
+
+    let mut weeks: Vec<WeekOvertime> = vec![];
+    for date in from
+        .iter_days()
+        .take_while(|date| *date <= now.date_naive())
+    {
+        let week_start = date - Duration::days(date.weekday().num_days_from_monday().into());
+        if weeks.last().map(|week| week.start) != Some(week_start) {
+            weeks.push(WeekOvertime::new(week_start));
+        }
+        let week = weeks.last_mut().unwrap();
+
+        let (Some(day_start), Some(day_end)) =
+            (start_of_day(date), start_of_day(date + Duration::days(1)))
+        else {
+            continue;
+        };
+        let scheduled = schedule::work_hours_on(settings, date)
+            .map(|(start, end)| (start.max(*first.time()), end.min(*now)))
+            .filter(|(start, end)| start < end);
+
+        for (start, end) in &periods {
+            let (start, end) = (*start.max(&day_start), *end.min(&day_end));
+            if start >= end {
+                continue;
+            }
+            let overlap = scheduled.map_or(Duration::zero(), |scheduled| {
+                overlap((start, end), scheduled)
+            });
+            week.worked = week.worked + (end - start);
+            week.extra = week.extra + (end - start - overlap);
+        }
+
+        if let Some((start, end)) = scheduled {
+            let worked: Duration = periods
+                .iter()
+                .map(|period| overlap(*period, (start, end)))
+                .fold(Duration::zero(), |total, overlap| total + overlap);
+            week.scheduled = week.scheduled + (end - start);
+            week.missing = week.missing + (end - start - worked);
+        }
+    }
+    weeks
+}
+
+fn overlap(
+    (a_start, a_end): (DateTime<Local>, DateTime<Local>),
+    (b_start, b_end): (DateTime<Local>, DateTime<Local>),
+) -> Duration {
+    (a_end.min(b_end) - a_start.max(b_start)).max(Duration::zero())
+}
+
+pub(crate) fn table(weeks: &[WeekOvertime], balance: Duration, since: NaiveDate) -> String {
+    let mut table = format!(
+        "{:<10}  {:>9}  {:>7}  {:>6}  {:>7}\n",
+        "week of", "scheduled", "worked", "extra", "missing"
+    );
+    for week in weeks {
+        let _ = writeln!(
+            table,
+            "{:<10}  {:>9}  {:>7}  {:>6}  {:>7}",
+            week.start,
+            hours_minutes(week.scheduled),
+            hours_minutes(week.worked),
+            hours_minutes(week.extra),
+            hours_minutes(week.missing)
+        );
+    }
+    let sign = if balance.num_minutes() < 0 { "" } else { "+" };
+    let _ = writeln!(
+        table,
+        "flexi-time balance since {since}: {sign}{}",
+        hours_minutes(balance)
+    );
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveTime, TimeZone};
+
+    use super::*;
+    use crate::{
+        settings::Settings,
+        transition::{Reason, Transition},
+    };
+
+    fn local(day: u32, hour: u32) -> DateTime<Local> {
+        // 2023-08-07 was a Monday.
+        Local
+            .with_ymd_and_hms(2023, 8, day, hour, 0, 0)
+            .single()
+            .unwrap()
+    }
+
+    fn entry(day: u32, hour: u32, to: ProfileType) -> HistoryEntry {
+        HistoryEntry::new(
+            local(day, hour),
+            &Transition::new(None, to, Reason::Auto),
+            None,
+        )
+    }
+
+    #[test]
+    fn weekly() {
+        let mut settings = Settings::default();
+        settings
+            .work_hours_mut()
+            .set_start(NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+        settings
+            .work_hours_mut()
+            .set_end(NaiveTime::from_hms_opt(17, 0, 0).unwrap());
+
+        let history = [
+            entry(7, 0, ProfileType::Home),
+            // Monday: starts an hour late and stays an hour late.
+            entry(7, 10, ProfileType::Work),
+            entry(7, 18, ProfileType::Home),
+            // Saturday: two hours of overtime.
+            entry(12, 10, ProfileType::Work),
+            entry(12, 12, ProfileType::Home),
+        ];
+        let weeks = super::weekly(
+            &history,
+            &settings,
+            NaiveDate::from_ymd_opt(2023, 8, 7).unwrap(),
+            &local(7, 23),
+        );
+        assert_eq!(
+            vec![WeekOvertime {
+                start: NaiveDate::from_ymd_opt(2023, 8, 7).unwrap(),
+                scheduled: Duration::hours(8),
+                worked: Duration::hours(8),
+                extra: Duration::hours(1),
+                missing: Duration::hours(1),
+            }],
+            weeks
+        );
+
+        let weeks = super::weekly(
+            &history,
+            &settings,
+            NaiveDate::from_ymd_opt(2023, 8, 12).unwrap(),
+            &local(13, 0),
+        );
+        assert_eq!(Duration::hours(2), weeks[0].balance());
+    }
+}
@@ -0,0 +1,54 @@
+use std::process::{Command, ExitStatus};
+
+use crate::{error::Error, result::Result};
+
+pub(crate) fn run(command: &mut Command) -> Result<()> {
+    let status = command.status()?;
+    log_exit(command, status);
+    if !status.success() {
+        return Err(Error::ChildProcessError {
+            name: name(command),
+            reason: status.code().into(),
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn output(command: &mut Command) -> Result<String> {
+    let output = command.output()?;
+    log_exit(command, output.status);
+    if !output.status.success() {
+        return Err(Error::ChildProcessError {
+            name: name(command),
+            reason: output.status.code().into(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub(crate) fn in_path(program: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| dir.join(program).is_file())
+}
+
+fn log_exit(command: &Command, status: ExitStatus) {
+    let line = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    log::debug!(command = line, exit_code = exit_code(status); "ran child process");
+}
+
+/// The exit code of a process, or `signal` if it was killed by one.
+pub(crate) fn exit_code(status: ExitStatus) -> String {
+    status
+        .code()
+        .map_or_else(|| "signal".into(), |code| code.to_string())
+}
+
+fn name(command: &Command) -> String {
+    command.get_program().to_string_lossy().to_string()
+}
@@ -2,109 +2,563 @@ use std::fs;
 use std::{ffi::OsStr, process::Command};
 
 use anyhow::Context;
-use chrono::{Datelike, Local};
-use gio::prelude::SettingsExt;
+use chrono::Local;
+use gio::prelude::AppInfoExt;
 use rand::seq::SliceRandom;
 
 use crate::{
+    action::{Action, Change, List},
+    app::LaunchedApp,
+    browser::BrowserWrapper,
+    desktop::{Desktop, DesktopKind},
+    environment::Environment,
+    error::{Error, StepFailure},
+    git::GitIdentity,
+    history,
+    hook::{FailurePolicy, Hook},
+    process,
     result::Result,
-    settings::{Override, Profile, ProfileType, Settings},
+    schedule,
+    settings::{LockScreen, Override, Profile, ProfileType, Settings},
+    transition::{Reason, Transition},
 };
 
 pub(crate) struct ProfileApplicator<'a> {
     settings: &'a Settings,
-    profile_type: ProfileType,
+    transition: Transition,
+    desktop: Box<dyn Desktop>,
+    dry_run: bool,
+    rollback_on_error: bool,
+    best_effort: bool,
+    launched_apps: Vec<LaunchedApp>,
+    git_include_installed: bool,
+}
+
+/// What applying a profile changed which must be remembered until the next run.
+pub(crate) struct Applied {
+    transition: Transition,
+    entered: bool,
+    launched_apps: Vec<LaunchedApp>,
+    git_include_installed: bool,
+}
+
+impl Applied {
+    pub(crate) fn transition(&self) -> &Transition {
+        &self.transition
+    }
+
+    /// Whether the new profile is in force, if only partly.
+    pub(crate) fn entered(&self) -> bool {
+        self.entered
+    }
+
+    /// The apps started by this or an earlier switch which are still to be closed.
+    pub(crate) fn launched_apps(&self) -> &[LaunchedApp] {
+        &self.launched_apps
+    }
+
+    /// Whether the git identity file has been added to the user's gitconfig.
+    pub(crate) fn git_include_installed(&self) -> bool {
+        self.git_include_installed
+    }
 }
 
 impl<'a> ProfileApplicator<'a> {
     pub(crate) fn new(settings: &'a Settings, profile_type: ProfileType) -> Self {
+        Self::with_reason(settings, profile_type, Reason::Manual)
+    }
+
+    fn with_reason(settings: &'a Settings, profile_type: ProfileType, reason: Reason) -> Self {
+        let desktop = settings
+            .desktop()
+            .unwrap_or_else(DesktopKind::detect)
+            .backend();
         Self {
             settings,
-            profile_type,
+            transition: Transition::new(settings.applied(), profile_type, reason),
+            desktop,
+            dry_run: false,
+            rollback_on_error: false,
+            best_effort: false,
+            launched_apps: settings.launched_apps().to_vec(),
+            git_include_installed: settings.git_include_installed(),
         }
     }
 
-    pub(crate) fn auto(settings: &'a Settings) -> Self {
-        let profile_type = settings
+    pub(crate) fn auto(settings: &'a Settings) -> Result<Self> {
+        let now = Local::now();
+        let r#override = settings
             .r#override()
-            .and_then(Override::advise_profile)
-            .unwrap_or_else(|| {
-                let now = Local::now();
-                use chrono::Weekday::*;
-                match now.weekday() {
-                    Sat | Sun => ProfileType::Home,
-                    _ => {
-                        let time = &now.time();
-                        let work_hours = settings.work_hours();
-                        if work_hours.start() <= time && time < work_hours.end() {
-                            ProfileType::Work
-                        } else {
-                            ProfileType::Home
-                        }
+            .filter(|r#override| r#override.advise_profile().is_some());
+        let (profile_type, reason) = match r#override {
+            Some(r#override) => (r#override.profile_type(), Reason::Override),
+            None => (schedule::scheduled_profile(settings, &now), Reason::Auto),
+        };
+
+        log::debug!(profile:% = profile_type, reason:% = reason; "chose profile");
+        if profile_type == ProfileType::Work && !r#override.is_some_and(Override::is_forced) {
+            if let Some(cap) = settings
+                .work_time_cap()
+                .reached(&history::read(settings.paths().history())?, &now)
+            {
+                if settings.applied() != Some(ProfileType::Home) {
+                    log::warn!("the {cap} has been reached, switching to home");
+                }
+                return Ok(Self::with_reason(settings, ProfileType::Home, Reason::Cap));
+            }
+        }
+        Ok(Self::with_reason(settings, profile_type, reason))
+    }
+
+    /// Print the planned changes rather than applying them.
+    pub(crate) fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Restore the previous values of all changed settings if any step fails.
+    pub(crate) fn rollback_on_error(mut self, rollback_on_error: bool) -> Self {
+        self.rollback_on_error = rollback_on_error;
+        self
+    }
+
+    /// Apply every step even if an earlier one fails, reporting all failures together.
+    pub(crate) fn best_effort(mut self, best_effort: bool) -> Self {
+        self.best_effort = best_effort;
+        self
+    }
+
+    pub(crate) fn transition(&self) -> Transition {
+        self.transition
+    }
+
+    /// Apply the profile, returning what must be remembered whether or not every step succeeded.
+    pub(crate) fn apply(mut self) -> (Applied, Result<()>) {
+        log::info!(
+            from = self.transition.from().map_or("none".into(), |from| from.to_string()),
+            to:% = self.transition.to(),
+            reason:% = self.transition.reason(),
+            dry_run = self.dry_run;
+            "applying profile"
+        );
+        let result = self.apply_profile(self.settings.profile(self.transition.to()));
+        let entered = match &result {
+            Ok(()) => true,
+            Err(Error::ApplyError { partial, .. }) => *partial,
+            Err(_) => false,
+        };
+        let applied = Applied {
+            transition: self.transition,
+            entered,
+            launched_apps: self.launched_apps,
+            git_include_installed: self.git_include_installed,
+        };
+        (applied, result)
+    }
+
+    fn apply_profile(&mut self, profile: &Profile) -> Result<()> {
+        let background = self.plan_background(profile);
+        let lock_screen = self.plan_lock_screen(profile, background.as_ref().ok());
+        let steps = [
+            ("browser", self.plan_browser(profile)),
+            ("background", background),
+            ("lock screen", lock_screen),
+            ("colour scheme", self.plan_colour_scheme(profile)),
+            ("default apps", self.plan_default_apps(profile)),
+            ("notifications", self.plan_notifications(profile)),
+            ("dock", self.plan_dock(profile)),
+            ("git identity", self.plan_git_identity(profile)),
+            ("environment", self.plan_environment(profile)),
+        ];
+
+        // Plan every step before applying any, so the current values form a snapshot to restore.
+        let mut planned = vec![];
+        let mut failures = vec![];
+        for (name, plan) in steps {
+            match skip_if_unsupported(name, plan) {
+                Ok(actions) => planned.push((name, actions)),
+                Err(e) => {
+                    failures.push(StepFailure::new(name, e));
+                    if !self.best_effort {
+                        return Err(Error::ApplyError {
+                            failures: failures.into(),
+                            partial: false,
+                        });
                     }
                 }
-            });
-        Self::new(settings, profile_type)
+            }
+        }
+
+        // Only leave the old profile once the new one is known to be applicable.
+        if let Some(from) = self
+            .transition
+            .from()
+            .filter(|_| self.transition.is_change())
+        {
+            let from = self.settings.profile(from);
+            self.close_apps(from, profile);
+            self.run_hooks("on-leave", from.on_leave())?;
+        }
+
+        let mut applied = vec![];
+        for (name, actions) in planned {
+            if let Err(e) = self.execute(name, actions, &mut applied) {
+                failures.push(StepFailure::new(name, e));
+                if !self.best_effort {
+                    break;
+                }
+            }
+        }
+
+        if !self.dry_run {
+            self.desktop.sync();
+        }
+        self.git_include_installed |= applied
+            .iter()
+            .any(|action| matches!(action, Action::GitInclude));
+
+        let rolled_back = !failures.is_empty() && self.rollback_on_error;
+        let partial = if rolled_back {
+            self.roll_back(&failures, applied)
+        } else {
+            !applied.is_empty()
+        };
+        let entering = !rolled_back
+            && (failures.is_empty() || self.best_effort)
+            && self.transition.is_change();
+        if entering {
+            match self.run_hooks("on-enter", profile.on_enter()) {
+                Ok(()) => self.launch_apps(profile),
+                Err(e) => failures.push(StepFailure::new("on-enter", e)),
+            }
+        }
+
+        if failures.is_empty() {
+            return Ok(());
+        }
+        Err(Error::ApplyError {
+            failures: failures.into(),
+            // Hooks which have run cannot be taken back.
+            partial: partial || entering,
+        })
     }
 
-    pub(crate) fn apply(&self) -> Result<()> {
-        match self.profile_type {
-            ProfileType::Work => self.apply_profile(self.settings.work()),
-            ProfileType::Home => self.apply_profile(self.settings.home()),
+    /// Run the hooks for leaving or entering a profile, following each one's failure policy.
+    fn run_hooks(&self, event: &str, hooks: &[Hook]) -> Result<()> {
+        for hook in hooks {
+            if self.dry_run {
+                println!("{event}: {}", hook.command());
+                continue;
+            }
+            let Err(e) = hook.run(&self.transition) else {
+                continue;
+            };
+            match hook.on_failure() {
+                FailurePolicy::Ignore => {}
+                FailurePolicy::Warn => log::warn!("{event} hook failed: {e}"),
+                FailurePolicy::Abort => {
+                    return Err(anyhow::Error::from(e)
+                        .context(format!("{event} hook aborted the switch"))
+                        .into())
+                }
+            }
         }
+        Ok(())
     }
 
-    fn apply_profile(&self, profile: &Profile) -> Result<()> {
-        self.set_browser(profile).context("failed to set profile")?;
-        self.set_background(profile)
-            .context("failed to set browser")?;
-        self.change_colour_scheme(profile)
-            .context("failed to set colour scheme")?;
+    /// Close the apps of the profile being left which commute started, except those which the new
+    /// profile also uses.
+    fn close_apps(&mut self, from: &Profile, to: &Profile) {
+        for app in from.apps().iter().filter(|app| !to.apps().contains(app)) {
+            if self.dry_run {
+                println!("close: {app}");
+                continue;
+            }
+            for launched in self
+                .launched_apps
+                .iter()
+                .filter(|launched| launched.app() == app)
+            {
+                match launched.close(from.app_close_timeout()) {
+                    Ok(()) => log::info!(app:% = app; "closed app"),
+                    Err(e) => log::warn!("failed to close {app}: {e}"),
+                }
+            }
+            self.launched_apps.retain(|launched| launched.app() != app);
+        }
+    }
 
-        gio::Settings::sync();
+    fn launch_apps(&mut self, profile: &Profile) {
+        self.launched_apps.retain(LaunchedApp::is_running);
+        for app in profile.apps() {
+            if self.dry_run {
+                println!("launch: {app}");
+                continue;
+            }
+            if self
+                .launched_apps
+                .iter()
+                .any(|launched| launched.app() == app)
+            {
+                continue;
+            }
+            match app.launch() {
+                Ok(Some(launched)) => {
+                    log::info!(app:% = app; "launched app");
+                    self.launched_apps.push(launched);
+                }
+                Ok(None) => log::debug!(app:% = app; "app already running"),
+                Err(e) => log::warn!("failed to launch {app}: {e}"),
+            }
+        }
+    }
+
+    /// Print or apply the actions planned for one step of applying a profile, recording those
+    /// which were applied.
+    fn execute(&self, name: &str, actions: Vec<Action>, applied: &mut Vec<Action>) -> Result<()> {
+        for action in actions {
+            if self.dry_run {
+                println!("{action}");
+                continue;
+            }
+            match action.apply(&*self.desktop) {
+                Ok(()) => {
+                    log::info!(step = name; "{action}");
+                    applied.push(action);
+                }
+                Err(e) if e.is_unsupported() => log::warn!("skipping {name}: {e}"),
+                Err(e) => return Err(e),
+            }
+        }
         Ok(())
     }
 
-    fn set_browser(&self, profile: &Profile) -> Result<()> {
-        let Some(browser) = profile.browser() else { return Ok(()); };
+    /// Restore the previous values of applied actions, most recent first, and report the outcome
+    /// of each. Returns whether any action could not be reverted and so is still applied.
+    fn roll_back(&self, failures: &[StepFailure], applied: Vec<Action>) -> bool {
+        for failure in failures {
+            log::warn!("{}: failed, rolling back", failure.step());
+        }
+        let mut partial = false;
+        for action in applied.iter().rev() {
+            let name = action.name();
+            let Some(reverse) = action.reverse() else {
+                log::warn!("{name}: applied, not reverted as its previous value is unknown");
+                partial = true;
+                continue;
+            };
+            match reverse.apply(&*self.desktop) {
+                Ok(()) => log::warn!("{name}: applied, then reverted"),
+                Err(e) => {
+                    log::warn!("{name}: applied, failed to revert: {e}");
+                    partial = true;
+                }
+            }
+        }
+        self.desktop.sync();
+        partial
+    }
 
-        let status = Command::new("xdg-settings")
-            .arg("set")
-            .arg("default-web-browser")
-            .arg(browser)
-            .status()?;
-        if !status.success() {
-            Err(crate::error::Error::ChildProcessError {
-                name: "xdg-settings".into(),
-                reason: status.code().into(),
-            })
-        } else {
-            Ok(())
+    fn plan_browser(&self, profile: &Profile) -> Result<Vec<Action>> {
+        let Some(browser) = profile.browser() else {
+            return Ok(vec![]);
+        };
+
+        let current_browser = process::output(
+            Command::new("xdg-settings")
+                .arg("get")
+                .arg("default-web-browser"),
+        )
+        .ok();
+        let Some(browser_profile) = profile.browser_profile() else {
+            return Ok(vec![Action::Browser(Change::new(current_browser, browser))]);
+        };
+
+        let wrapper = BrowserWrapper::new(browser, browser_profile)?;
+        let browser = wrapper.id().to_string();
+        Ok(vec![
+            Action::BrowserWrapper(wrapper),
+            Action::Browser(Change::new(current_browser, browser)),
+        ])
+    }
+
+    fn plan_default_apps(&self, profile: &Profile) -> Result<Vec<Action>> {
+        let mut actions = vec![];
+        for (mime_type, app) in profile.default_apps() {
+            let app_info = gio::DesktopAppInfo::new(app)
+                .ok_or_else(|| Error::UnknownAppError { id: app.clone() })?;
+
+            let mime_type = mime_type_of(mime_type);
+            if !app_info.supported_types().iter().any(|t| *t == mime_type) {
+                log::warn!("{app} does not declare that it can open {mime_type}");
+            }
+            let current = process::output(
+                Command::new("xdg-mime")
+                    .args(["query", "default"])
+                    .arg(&mime_type),
+            )
+            .ok()
+            .filter(|current| !current.is_empty());
+            actions.push(Action::MimeDefault {
+                mime_type,
+                change: Change::new(current, app),
+            });
+        }
+        Ok(actions)
+    }
+
+    fn plan_notifications(&self, profile: &Profile) -> Result<Vec<Action>> {
+        let notifications = profile.notifications();
+        let mut actions = vec![];
+
+        if let Some(show_banners) = notifications.show_banners() {
+            let current = self.desktop.notification_banners()?;
+            actions.push(Action::NotificationBanners(Change::new(
+                current,
+                show_banners,
+            )));
         }
+
+        for (app, enabled) in notifications.apps() {
+            let current = self.desktop.app_notifications(app)?;
+            actions.push(Action::AppNotifications {
+                app: app.clone(),
+                change: Change::new(current, *enabled),
+            });
+        }
+
+        Ok(actions)
+    }
+
+    fn plan_dock(&self, profile: &Profile) -> Result<Vec<Action>> {
+        let mut actions = vec![];
+
+        if let Some(favourite_apps) = profile.favourite_apps() {
+            let current = self.desktop.favourite_apps()?.map(List::from);
+            actions.push(Action::FavouriteApps(Change::new(
+                current,
+                favourite_apps.to_vec(),
+            )));
+        }
+
+        let extensions = profile.extensions();
+        if extensions.is_empty() {
+            return Ok(actions);
+        }
+
+        let installed = self.desktop.installed_extensions()?;
+        for uuid in extensions.enable().iter().chain(extensions.disable()) {
+            if !installed.contains(uuid) {
+                log::warn!("extension {uuid} is not installed");
+            }
+        }
+
+        let current_enabled = self.desktop.enabled_extensions()?;
+        let current_disabled = self.desktop.disabled_extensions()?;
+        let enabled = toggle(
+            current_enabled.as_deref().unwrap_or_default(),
+            extensions.enable(),
+            extensions.disable(),
+        );
+        let disabled = toggle(
+            current_disabled.as_deref().unwrap_or_default(),
+            extensions.disable(),
+            extensions.enable(),
+        );
+        actions.push(Action::EnabledExtensions(Change::new(
+            current_enabled.map(List::from),
+            enabled,
+        )));
+        actions.push(Action::DisabledExtensions(Change::new(
+            current_disabled.map(List::from),
+            disabled,
+        )));
+        Ok(actions)
+    }
+
+    fn plan_git_identity(&self, profile: &Profile) -> Result<Vec<Action>> {
+        let Some(identity) = profile.git() else {
+            return Ok(vec![]);
+        };
+        let current = GitIdentity::current()?;
+        let mut actions = vec![Action::GitIdentity(Change::new(current, identity.clone()))];
+        if !self.settings.git_include_installed() {
+            actions.push(Action::GitInclude);
+        }
+        Ok(actions)
     }
 
-    fn set_background(&self, profile: &Profile) -> Result<()> {
-        let Some(background_dir) = profile.background_dir() else { return Ok(()); };
+    fn plan_environment(&self, profile: &Profile) -> Result<Vec<Action>> {
+        let environment = Environment::from(profile.env().clone());
+        let current = Environment::current()?;
+        if current.is_none() && environment.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(vec![Action::Environment(Change::new(current, environment))])
+    }
 
-        let background_settings = gio::Settings::new("org.gnome.desktop.background");
-        let current_background_uri = background_settings.string("picture-uri-dark").to_string();
+    fn plan_background(&self, profile: &Profile) -> Result<Vec<Action>> {
+        let Some(background_dir) = profile.background_dir() else {
+            return Ok(vec![]);
+        };
 
+        let current_background = self.desktop.background()?;
+        Ok(self
+            .pick_background(
+                background_dir,
+                current_background
+                    .as_ref()
+                    .map(|background| &background.dark),
+            )?
+            .map(|uri| Action::Background(Change::new(current_background, uri)))
+            .into_iter()
+            .collect())
+    }
+
+    /// Plan the lock screen background, mirroring the desktop background planned in `background`
+    /// if the profile asks for this.
+    fn plan_lock_screen(
+        &self,
+        profile: &Profile,
+        background: Option<&Vec<Action>>,
+    ) -> Result<Vec<Action>> {
+        let Some(lock_screen) = profile.lock_screen() else {
+            return Ok(vec![]);
+        };
+
+        let current = self.desktop.lock_screen_background()?;
+        let target = match lock_screen {
+            LockScreen::MirrorBackground => {
+                let planned = background
+                    .into_iter()
+                    .flatten()
+                    .find_map(|action| match action {
+                        Action::Background(change) => Some(change.target().dark.clone()),
+                        _ => None,
+                    });
+                match planned {
+                    Some(planned) => Some(planned),
+                    None => self.desktop.background()?.map(|background| background.dark),
+                }
+            }
+            LockScreen::Dir(dir) => self.pick_background(dir, current.as_ref())?,
+        };
+        Ok(target
+            .map(|uri| Action::LockScreen(Change::new(current, uri)))
+            .into_iter()
+            .collect())
+    }
+
+    /// Pick a random image from a directory, other than the one currently shown.
+    fn pick_background(&self, dir: &str, current: Option<&String>) -> Result<Option<String>> {
         let bkg_uris = {
             let mut bkg_uris = self
-                .available_backgrounds(background_dir)
-                .context(format!("failed to find backgrounds in {}", background_dir))?;
+                .available_backgrounds(dir)
+                .context(format!("failed to find backgrounds in {}", dir))?;
             bkg_uris.shuffle(&mut rand::thread_rng());
             bkg_uris
         };
-        if let Some(uri) = bkg_uris.iter().find(|u| *u != &current_background_uri) {
-            background_settings
-                .set_string("picture-uri", uri.as_ref())
-                .context("failed to set picture-uri")?;
-            background_settings
-                .set_string("picture-uri-dark", uri.as_ref())
-                .context("failed to set picture-uri")?;
-        }
-        Ok(())
+        Ok(bkg_uris.into_iter().find(|u| Some(u) != current))
     }
 
     fn available_backgrounds(&self, background_dir: &str) -> Result<Vec<String>> {
@@ -112,7 +566,7 @@ impl<'a> ProfileApplicator<'a> {
             .filter_map(|d| {
                 if let Ok(d) = d {
                     let path = d.path();
-                    let extension = path.extension().map(OsStr::to_str).flatten();
+                    let extension = path.extension().and_then(OsStr::to_str);
                     match extension {
                         Some("png") | Some("jpg") | Some("jpeg") => Some(path),
                         _ => None,
@@ -125,22 +579,143 @@ impl<'a> ProfileApplicator<'a> {
             .collect())
     }
 
-    fn change_colour_scheme(&self, profile: &Profile) -> Result<()> {
-        let desktop_settings = gio::Settings::new("org.gnome.desktop.interface");
+    fn plan_colour_scheme(&self, profile: &Profile) -> Result<Vec<Action>> {
         let theme = profile.theme();
+        let mut actions = vec![];
 
         if let Some(gtk_theme) = theme.gtk() {
-            desktop_settings
-                .set_string("gtk-theme", gtk_theme)
-                .context("failed to set gtk theme")?;
+            let current = self.desktop.gtk_theme()?;
+            actions.push(Action::GtkTheme(Change::new(current, gtk_theme)));
         }
 
         if let Some(icon_theme) = theme.icons() {
-            desktop_settings
-                .set_string("icon-theme", icon_theme)
-                .context("failed to set icon theme")?;
+            let current = self.desktop.icon_theme()?;
+            actions.push(Action::IconTheme(Change::new(current, icon_theme)));
         }
 
-        Ok(())
+        Ok(actions)
+    }
+}
+
+/// Add and remove values from a list, keeping the order of those already present.
+fn toggle(current: &[String], add: &[String], remove: &[String]) -> Vec<String> {
+    let mut values: Vec<String> = current
+        .iter()
+        .filter(|value| !remove.contains(value))
+        .cloned()
+        .collect();
+    for value in add {
+        if !values.contains(value) {
+            values.push(value.clone());
+        }
+    }
+    values
+}
+
+/// Expand URL schemes such as `mailto:` into the MIME types which represent them.
+fn mime_type_of(mime_type_or_scheme: &str) -> String {
+    match mime_type_or_scheme.strip_suffix(':') {
+        Some(scheme) => format!("x-scheme-handler/{scheme}"),
+        None => mime_type_or_scheme.into(),
+    }
+}
+
+/// Allow the rest of a profile to be applied when the desktop lacks the settings for one step.
+fn skip_if_unsupported<T: Default>(step: &str, result: Result<T>) -> Result<T> {
+    match result {
+        Err(e) if e.is_unsupported() => {
+            log::warn!("skipping {step}: {e}");
+            Ok(T::default())
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+
+    use crate::desktop::Background;
+
+    use super::*;
+
+    /// A desktop on which only the GTK theme can be changed.
+    struct GtkOnly;
+
+    impl Desktop for GtkOnly {
+        fn background(&self) -> Result<Option<Background>> {
+            Ok(None)
+        }
+
+        fn set_background(&self, _background: &Background) -> Result<()> {
+            Err(anyhow!("cannot set background").into())
+        }
+
+        fn gtk_theme(&self) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn set_gtk_theme(&self, _theme: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn icon_theme(&self) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn set_icon_theme(&self, _theme: &str) -> Result<()> {
+            Err(anyhow!("cannot set icon theme").into())
+        }
+    }
+
+    #[test]
+    fn roll_back() {
+        let settings = Settings::default();
+        let mut applicator = ProfileApplicator::new(&settings, ProfileType::Work);
+        applicator.desktop = Box::new(GtkOnly);
+
+        let reverted = Action::GtkTheme(Change::new(Some("Adwaita".into()), "Yaru"));
+        assert!(!applicator.roll_back(&[], vec![reverted]));
+
+        let unknown = Action::GtkTheme(Change::new(None, "Yaru"));
+        assert!(applicator.roll_back(&[], vec![unknown]));
+
+        let failed = Action::IconTheme(Change::new(Some("Adwaita".into()), "Yaru"));
+        assert!(applicator.roll_back(&[], vec![failed]));
+    }
+
+    #[test]
+    fn toggle() {
+        let list = |values: &[&str]| -> Vec<String> {
+            values.iter().map(|value| value.to_string()).collect()
+        };
+        assert_eq!(
+            list(&["a", "c", "d"]),
+            super::toggle(
+                &list(&["a", "b", "c"]),
+                &list(&["c", "d"]),
+                &list(&["b", "e"])
+            )
+        );
+        assert_eq!(list(&["a"]), super::toggle(&[], &list(&["a", "a"]), &[]));
+    }
+
+    #[test]
+    fn mime_type_of() {
+        assert_eq!("x-scheme-handler/mailto", super::mime_type_of("mailto:"));
+        assert_eq!("application/pdf", super::mime_type_of("application/pdf"));
+    }
+
+    #[test]
+    fn run_hooks() {
+        let settings = Settings::default();
+        let applicator = ProfileApplicator::new(&settings, ProfileType::Work);
+        let hooks = |on_failure: &str| -> Vec<Hook> {
+            serde_yaml::from_str(&format!("- command: exit 1\n  on-failure: {on_failure}\n"))
+                .unwrap()
+        };
+        assert!(applicator.run_hooks("on-enter", &hooks("ignore")).is_ok());
+        assert!(applicator.run_hooks("on-enter", &hooks("warn")).is_ok());
+        assert!(applicator.run_hooks("on-enter", &hooks("abort")).is_err());
     }
 }
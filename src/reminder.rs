@@ -0,0 +1,154 @@
+use chrono::{offset::Utc, serde::ts_seconds, DateTime, Duration, Local};
+use serde::{Deserialize as Deserialise, Serialize as Serialise};
+
+use crate::{
+    hook::Hook,
+    notifier::{Notification, Notifier},
+    result::Result,
+    schedule,
+    settings::{Override, ProfileType, Settings},
+    transition::{Reason, Transition},
+};
+
+/// How much longer the current profile is kept when the user asks to stay.
+const EXTENSION_HOURS: i64 = 1;
+
+/// A warning given some time before the profile switches.
+#[derive(Clone, Debug, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Reminder {
+    /// Minutes before the switch.
+    before: u64,
+
+    /// Only remind before switching to this profile.
+    to: Option<ProfileType>,
+
+    /// Run this hook instead of sending a notification.
+    run: Option<Hook>,
+}
+
+/// A record that a reminder was given, so that it is not given again.
+#[derive(Clone, Debug, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct SentReminder {
+    before: u64,
+
+    /// When the switch reminded about was due.
+    #[serde(with = "ts_seconds")]
+    switch: DateTime<Utc>,
+}
+
+/// Give any reminders which are due for the next switch and have not been given yet. The caller
+/// saves the settings afterwards to record those given.
+pub(crate) fn send_due(
+    settings: &mut Settings,
+    now: &DateTime<Local>,
+    dry_run: bool,
+) -> Result<()> {
+    settings.forget_sent_reminders_before(now);
+    let Some((switch, to)) = schedule::next_switch(settings, now) else {
+        return Ok(());
+    };
+    let from = schedule::profile_at(settings, now);
+
+    let due: Vec<Reminder> = settings
+        .reminders()
+        .iter()
+        .filter(|reminder| reminder.to.is_none() || reminder.to == Some(to))
+        .filter(|reminder| *now >= switch - Duration::minutes(reminder.before as i64))
+        .filter(|reminder| !settings.sent_reminder(reminder.before, &switch))
+        .cloned()
+        .collect();
+    for reminder in due {
+        if dry_run {
+            println!("reminder: switching to {to} at {}", switch.format("%H:%M"));
+            continue;
+        }
+
+        log::info!(before = reminder.before, to:% = to; "sending reminder");
+        settings.record_sent_reminder(SentReminder {
+            before: reminder.before,
+            switch: switch.into(),
+        });
+
+        let stay = match &reminder.run {
+            Some(hook) => {
+                if let Err(e) = hook.run(&Transition::new(Some(from), to, Reason::Auto)) {
+                    log::warn!("reminder hook failed: {e}");
+                }
+                false
+            }
+            None => notify(settings, &switch, to).unwrap_or_else(|e| {
+                log::warn!("failed to send reminder: {e}");
+                false
+            }),
+        };
+        if stay {
+            let until = switch + Duration::hours(EXTENSION_HOURS);
+            settings.set_override(Override::new(from, until - Local::now()));
+        }
+    }
+    Ok(())
+}
+
+/// Warn of the switch with a notification, returning whether the user chose to stay longer.
+fn notify(settings: &Settings, switch: &DateTime<Local>, to: ProfileType) -> Result<bool> {
+    let minutes = (*switch - Local::now()).num_minutes().max(0);
+    let notification = Notification::new(
+        format!("Switching to {to} in {minutes} minutes"),
+        format!(
+            "The {to} profile will be applied at {}.",
+            switch.format("%H:%M")
+        ),
+    )
+    .action("stay", "Stay 1 more hour");
+    let action = Notifier::session()?.notify(
+        &notification,
+        settings.switch_notification().action_timeout(),
+    )?;
+    Ok(action.as_deref() == Some("stay"))
+}
+
+impl SentReminder {
+    pub(crate) fn is_for(&self, before: u64, switch: &DateTime<Local>) -> bool {
+        self.before == before && self.switch == *switch
+    }
+
+    pub(crate) fn switch(&self) -> DateTime<Local> {
+        self.switch.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn send_due() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("reminders");
+        let mut settings = Settings::default();
+        settings.add_reminder(
+            serde_yaml::from_str(&format!(
+                "before: 15\nrun:\n  command: echo $COMMUTE_NEW_PROFILE >> '{}'\n",
+                out.display()
+            ))
+            .unwrap(),
+        );
+        // 2023-08-07 was a Monday, and the default work hours end at 18:30.
+        let switch = Local.with_ymd_and_hms(2023, 8, 7, 18, 30, 0).unwrap();
+        let now = switch - Duration::minutes(10);
+
+        super::send_due(&mut settings, &now, false).unwrap();
+        super::send_due(&mut settings, &(now + Duration::minutes(1)), false).unwrap();
+        assert_eq!("home\n", fs::read_to_string(&out).unwrap());
+        assert!(settings.sent_reminder(15, &switch));
+
+        super::send_due(&mut settings, &(switch + Duration::minutes(1)), false).unwrap();
+        assert!(!settings.sent_reminder(15, &switch));
+    }
+}
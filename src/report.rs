@@ -0,0 +1,208 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::Serialize as Serialise;
+
+use crate::{
+    history::{self, HistoryEntry},
+    result::Result,
+    settings::ProfileType,
+};
+
+/// The time spent in each profile on one day.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DayTotals {
+    date: NaiveDate,
+    work: Duration,
+    home: Duration,
+}
+
+impl DayTotals {
+    fn new(date: NaiveDate) -> Self {
+        Self {
+            date,
+            work: Duration::zero(),
+            home: Duration::zero(),
+        }
+    }
+
+    pub(crate) fn work(&self) -> Duration {
+        self.work
+    }
+
+    fn add(&mut self, profile_type: ProfileType, duration: Duration) {
+        match profile_type {
+            ProfileType::Work => self.work = self.work + duration,
+            ProfileType::Home => self.home = self.home + duration,
+        }
+    }
+}
+
+/// Sum the time spent in each profile on each day from `from` to `to` inclusive, counting the
+/// profile applied last as in force until `now`.
+pub(crate) fn daily_totals(
+    history: &[HistoryEntry],
+    from: NaiveDate,
+    to: NaiveDate,
+    now: &DateTime<Local>,
+) -> Vec<DayTotals> {
+    let mut days: Vec<DayTotals> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(DayTotals::new)
+        .collect();
+
+    for profile_type in [ProfileType::Work, ProfileType::Home] {
+        for (mut start, end) in history::periods(history, profile_type, now) {
+            while start < end {
+                let date = start.date_naive();
+                let until = start_of_day(date + Duration::days(1))
+                    .unwrap_or(end)
+                    .min(end);
+                if let Some(day) = days.iter_mut().find(|day| day.date == date) {
+                    day.add(profile_type, until - start);
+                }
+                start = until;
+            }
+        }
+    }
+    days
+}
+
+/// The first moment of the given day in the local time zone.
+pub(crate) fn start_of_day(date: NaiveDate) -> Option<DateTime<Local>> {
+    date.and_hms_opt(0, 0, 0)?
+        .and_local_timezone(Local)
+        .earliest()
+}
+
+/// One row of a report, with durations in hours.
+#[derive(Debug, Serialise)]
+#[serde(rename_all = "kebab-case")]
+struct Row {
+    date: String,
+    work_hours: f64,
+    home_hours: f64,
+}
+
+impl From<&DayTotals> for Row {
+    fn from(day: &DayTotals) -> Self {
+        Self {
+            date: day.date.to_string(),
+            work_hours: hours(day.work),
+            home_hours: hours(day.home),
+        }
+    }
+}
+
+pub(crate) fn table(days: &[DayTotals]) -> String {
+    let mut table = format!("{:<10}  {:>6}  {:>6}\n", "date", "work", "home");
+    let mut total = DayTotals::new(NaiveDate::MIN);
+    for day in days {
+        let _ = writeln!(
+            table,
+            "{:<10}  {:>6}  {:>6}",
+            day.date,
+            hours_minutes(day.work),
+            hours_minutes(day.home)
+        );
+        total.add(ProfileType::Work, day.work);
+        total.add(ProfileType::Home, day.home);
+    }
+    let _ = writeln!(
+        table,
+        "{:<10}  {:>6}  {:>6}",
+        "total",
+        hours_minutes(total.work),
+        hours_minutes(total.home)
+    );
+    table
+}
+
+pub(crate) fn csv(days: &[DayTotals]) -> String {
+    let mut csv = String::from("date,work-hours,home-hours\n");
+    for row in days.iter().map(Row::from) {
+        let _ = writeln!(
+            csv,
+            "{},{:.2},{:.2}",
+            row.date, row.work_hours, row.home_hours
+        );
+    }
+    csv
+}
+
+pub(crate) fn json(days: &[DayTotals]) -> Result<String> {
+    let rows: Vec<Row> = days.iter().map(Row::from).collect();
+    Ok(serde_json::to_string_pretty(&rows)? + "\n")
+}
+
+fn hours(duration: Duration) -> f64 {
+    (duration.num_seconds() as f64 / 3600.0 * 100.0).round() / 100.0
+}
+
+pub(crate) fn hours_minutes(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    let sign = if minutes < 0 { "-" } else { "" };
+    let minutes = minutes.abs();
+    format!("{sign}{}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::transition::{Reason, Transition};
+
+    fn entry(day: u32, hour: u32, to: ProfileType) -> HistoryEntry {
+        HistoryEntry::new(
+            local(day, hour),
+            &Transition::new(None, to, Reason::Auto),
+            None,
+        )
+    }
+
+    fn local(day: u32, hour: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2023, 8, day, hour, 0, 0)
+            .single()
+            .unwrap()
+    }
+
+    #[test]
+    fn daily_totals() {
+        let history = [
+            entry(6, 20, ProfileType::Home),
+            entry(7, 9, ProfileType::Work),
+            entry(7, 17, ProfileType::Home),
+            entry(7, 22, ProfileType::Work),
+        ];
+        let days = super::daily_totals(
+            &history,
+            NaiveDate::from_ymd_opt(2023, 8, 7).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 8, 8).unwrap(),
+            &local(8, 1),
+        );
+        assert_eq!(
+            vec![
+                DayTotals {
+                    date: NaiveDate::from_ymd_opt(2023, 8, 7).unwrap(),
+                    work: Duration::hours(10),
+                    home: Duration::hours(14),
+                },
+                DayTotals {
+                    date: NaiveDate::from_ymd_opt(2023, 8, 8).unwrap(),
+                    work: Duration::hours(1),
+                    home: Duration::zero(),
+                },
+            ],
+            days
+        );
+    }
+
+    #[test]
+    fn hours_minutes() {
+        assert_eq!("8:05", super::hours_minutes(Duration::minutes(485)));
+        assert_eq!("-0:30", super::hours_minutes(Duration::minutes(-30)));
+    }
+}
@@ -0,0 +1,162 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Weekday};
+
+use crate::settings::{ProfileType, Settings, WorkHours};
+
+/// The number of days to look ahead for the next switch, enough to cover a weekend.
+const LOOKAHEAD_DAYS: i64 = 7;
+
+/// The work hours on the given day if it is a weekday, whether or not it is a leave day.
+pub(crate) fn weekday_hours(
+    work_hours: &WorkHours,
+    date: NaiveDate,
+) -> Option<(DateTime<Local>, DateTime<Local>)> {
+    use Weekday::*;
+    if matches!(date.weekday(), Sat | Sun) {
+        return None;
+    }
+    let start = date
+        .and_time(*work_hours.start())
+        .and_local_timezone(Local)
+        .earliest()?;
+    let end = date
+        .and_time(*work_hours.end())
+        .and_local_timezone(Local)
+        .earliest()?;
+    Some((start, end))
+}
+
+/// The work hours on the given day, if it is neither a weekend nor a leave day.
+pub(crate) fn work_hours_on(
+    settings: &Settings,
+    date: NaiveDate,
+) -> Option<(DateTime<Local>, DateTime<Local>)> {
+    if settings.leave_days().contains(&date) {
+        return None;
+    }
+    weekday_hours(settings.work_hours(), date)
+}
+
+/// The profile the work schedule calls for at the given time, ignoring overrides.
+pub(crate) fn scheduled_profile(settings: &Settings, time: &DateTime<Local>) -> ProfileType {
+    match work_hours_on(settings, time.date_naive()) {
+        Some((start, end)) if start <= *time && *time < end => ProfileType::Work,
+        _ => ProfileType::Home,
+    }
+}
+
+/// The profile in force at the given time, taking any override into account.
+pub(crate) fn profile_at(settings: &Settings, time: &DateTime<Local>) -> ProfileType {
+    settings
+        .r#override()
+        .filter(|r#override| *time < r#override.until())
+        .map(|r#override| r#override.profile_type())
+        .unwrap_or_else(|| scheduled_profile(settings, time))
+}
+
+/// When the profile in force next changes after the given time, and the profile it changes to.
+pub(crate) fn next_switch(
+    settings: &Settings,
+    after: &DateTime<Local>,
+) -> Option<(DateTime<Local>, ProfileType)> {
+    let current = profile_at(settings, after);
+    boundaries(settings, after.date_naive(), LOOKAHEAD_DAYS)
+        .into_iter()
+        .filter(|time| time > after)
+        .map(|time| (time, profile_at(settings, &time)))
+        .find(|(_, profile_type)| *profile_type != current)
+}
+
+/// The periods from `from` to `to` in which the work profile is in force, taking any override
+/// into account.
+pub(crate) fn work_periods(
+    settings: &Settings,
+    from: &DateTime<Local>,
+    to: &DateTime<Local>,
+) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+    let days = (to.date_naive() - from.date_naive()).num_days();
+    let mut times = vec![*from];
+    times.extend(
+        boundaries(settings, from.date_naive(), days)
+            .into_iter()
+            .filter(|time| from < time && time < to),
+    );
+    times.push(*to);
+    times.dedup();
+
+    let mut periods: Vec<(DateTime<Local>, DateTime<Local>)> = vec![];
+    for window in times.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        if profile_at(settings, &start) != ProfileType::Work {
+            continue;
+        }
+        match periods.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => periods.push((start, end)),
+        }
+    }
+    periods
+}
+
+/// The times at which the profile in force may change over the given number of days after
+/// `from`, in order.
+fn boundaries(settings: &Settings, from: NaiveDate, days: i64) -> Vec<DateTime<Local>> {
+    let work_hours = settings.work_hours();
+    let mut boundaries: Vec<DateTime<Local>> = (0..=days)
+        .map(|days| from + Duration::days(days))
+        .flat_map(|date| {
+            [
+                date.and_time(*work_hours.start()),
+                date.and_time(*work_hours.end()),
+            ]
+        })
+        .filter_map(|time| time.and_local_timezone(Local).earliest())
+        .collect();
+    if let Some(r#override) = settings.r#override() {
+        boundaries.push(r#override.until());
+    }
+    boundaries.sort();
+    boundaries
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveTime, TimeZone};
+
+    use super::*;
+
+    fn settings() -> Settings {
+        let mut settings = Settings::default();
+        settings
+            .work_hours_mut()
+            .set_start(NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+        settings
+            .work_hours_mut()
+            .set_end(NaiveTime::from_hms_opt(17, 0, 0).unwrap());
+        settings
+    }
+
+    fn local(day: u32, hour: u32) -> DateTime<Local> {
+        // 2023-08-07 was a Monday.
+        Local
+            .with_ymd_and_hms(2023, 8, day, hour, 0, 0)
+            .single()
+            .unwrap()
+    }
+
+    #[test]
+    fn next_switch() {
+        let settings = settings();
+        assert_eq!(
+            Some((local(7, 17), ProfileType::Home)),
+            super::next_switch(&settings, &local(7, 12))
+        );
+        assert_eq!(
+            Some((local(8, 9), ProfileType::Work)),
+            super::next_switch(&settings, &local(7, 17))
+        );
+        assert_eq!(
+            Some((local(14, 9), ProfileType::Work)),
+            super::next_switch(&settings, &local(11, 18))
+        );
+    }
+}
@@ -1,108 +1,469 @@
 use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Display,
     fs::{self, OpenOptions},
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::Duration as StdDuration,
 };
 
 use anyhow::Context;
-use chrono::{offset::Utc, serde::ts_seconds, DateTime, Duration, Local, NaiveTime};
+use chrono::{
+    offset::Utc, serde::ts_seconds, DateTime, Duration, Local, NaiveDate, NaiveTime, SubsecRound,
+};
 use directories::ProjectDirs;
 use lazy_static::lazy_static;
-use serde::{Deserialize as Deserialise, Serialize as Serialise};
+use serde::{de::DeserializeOwned, Deserialize as Deserialise, Serialize as Serialise};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    app::{App, LaunchedApp},
+    cap::WorkTimeCap,
+    desktop::DesktopKind,
+    git::GitIdentity,
+    hook::Hook,
+    migration::{self, SETTINGS_VERSION, STATE_VERSION},
+    reminder::{Reminder, SentReminder},
+    result::Result,
+};
+
+const DEFAULT_APP_CLOSE_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_ACTION_TIMEOUT_SECS: u64 = 60;
+
+/// The environment variable which names the config file to use instead of the default.
+const CONFIG_VAR: &str = "COMMUTE_CONFIG";
 
-use crate::result::Result;
+/// The environment variable which names the directory to keep the state file in.
+const STATE_DIR_VAR: &str = "COMMUTE_STATE_DIR";
+
+const STATE_FILE_NAME: &str = "state.yml";
+const HISTORY_FILE_NAME: &str = "history.jsonl";
+const LOG_FILE_NAME: &str = "commute.log";
 
 lazy_static! {
-    static ref SETTINGS_PATH: PathBuf = {
+    static ref CONFIG_PATH: PathBuf = {
+        ProjectDirs::from("net", "kcza", env!("CARGO_PKG_NAME"))
+            .unwrap()
+            .config_dir()
+            .join("config.yml")
+    };
+    static ref STATE_PATH: PathBuf = {
+        let dirs = ProjectDirs::from("net", "kcza", env!("CARGO_PKG_NAME")).unwrap();
+        dirs.state_dir()
+            .unwrap_or_else(|| dirs.data_local_dir())
+            .join(STATE_FILE_NAME)
+    };
+
+    /// Where the history and log are kept by default.
+    static ref DATA_DIR: PathBuf = {
         ProjectDirs::from("net", "kcza", env!("CARGO_PKG_NAME"))
             .unwrap()
             .data_local_dir()
-            .join("settings.yml")
+            .into()
     };
+
+    /// Where the config and state were kept together before being split.
+    static ref LEGACY_SETTINGS_PATH: PathBuf = DATA_DIR.join("settings.yml");
 }
 
-#[derive(Debug, Serialise, Deserialise)]
-#[serde(rename_all = "kebab-case")]
+/// The user's config together with the state kept between runs.
+#[derive(Debug, Default)]
 pub(crate) struct Settings {
+    config: Config,
+    state: State,
+    paths: SettingsPaths,
+
+    /// The legacy settings file these settings were read from, to be moved aside once saved.
+    migrated_from: Option<PathBuf>,
+
+    /// The version of the config file, if it was older and so must be backed up before saving.
+    upgraded_from: Option<u64>,
+}
+
+/// The files which hold the config, state, history and log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SettingsPaths {
+    config: PathBuf,
+    state: PathBuf,
+    history: PathBuf,
+    log: PathBuf,
+}
+
+impl SettingsPaths {
+    /// Choose the files to use, preferring the given config file, then those named by the
+    /// environment, then the defaults.
+    pub(crate) fn new(config: Option<&Path>) -> Self {
+        Self::resolve(
+            config.map(Into::into),
+            env_path(CONFIG_VAR),
+            env_path(STATE_DIR_VAR),
+        )
+    }
+
+    /// Choose the files to use from the config file argument and the values of the environment
+    /// variables, if set.
+    fn resolve(
+        config: Option<PathBuf>,
+        config_var: Option<PathBuf>,
+        state_dir_var: Option<PathBuf>,
+    ) -> Self {
+        let config = config.or(config_var).unwrap_or_else(|| CONFIG_PATH.clone());
+        match state_dir_var {
+            // Keep everything written between runs together, so that the run is kept apart.
+            Some(dir) => Self {
+                config,
+                state: dir.join(STATE_FILE_NAME),
+                history: dir.join(HISTORY_FILE_NAME),
+                log: dir.join(LOG_FILE_NAME),
+            },
+            None => Self {
+                config,
+                ..Self::default()
+            },
+        }
+    }
+
+    pub(crate) fn config(&self) -> &Path {
+        &self.config
+    }
+
+    pub(crate) fn state(&self) -> &Path {
+        &self.state
+    }
+
+    pub(crate) fn history(&self) -> &Path {
+        &self.history
+    }
+
+    pub(crate) fn log(&self) -> &Path {
+        &self.log
+    }
+}
+
+impl Default for SettingsPaths {
+    fn default() -> Self {
+        Self {
+            config: CONFIG_PATH.clone(),
+            state: STATE_PATH.clone(),
+            history: DATA_DIR.join(HISTORY_FILE_NAME),
+            log: DATA_DIR.join(LOG_FILE_NAME),
+        }
+    }
+}
+
+fn env_path(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Settings which the user chooses, kept in a file suitable for dotfiles.
+#[derive(Debug, Serialise, Deserialise)]
+#[serde(default, rename_all = "kebab-case")]
+pub(crate) struct Config {
+    version: u64,
     work: Profile,
     home: Profile,
     work_hours: WorkHours,
+    desktop: Option<DesktopKind>,
+    switch_notification: SwitchNotification,
+    reminders: Vec<Reminder>,
+    work_time_cap: WorkTimeCap,
+
+    /// Weekdays off work, on which the home profile is kept all day.
+    leave_days: Vec<NaiveDate>,
+
+    #[serde(skip)]
+    dirty: bool,
+}
+
+/// What `commute` records about itself between runs.
+#[derive(Debug, Serialise, Deserialise)]
+#[serde(rename_all = "kebab-case")]
+struct State {
+    version: u64,
     r#override: Option<Override>,
 
+    /// The profile most recently applied.
+    applied: Option<ProfileType>,
+
+    /// Reminders already given for upcoming switches.
+    #[serde(default)]
+    sent_reminders: Vec<SentReminder>,
+
+    /// The day from which the flexi-time balance is counted.
+    flexi_reset: Option<NaiveDate>,
+
+    /// Apps started when a profile was entered, to close when it is left.
+    #[serde(default)]
+    launched_apps: Vec<LaunchedApp>,
+
+    /// Whether the git identity file has been added to the user's gitconfig, which is then left
+    /// for the user to edit.
+    #[serde(default)]
+    git_include_installed: bool,
+
     #[serde(skip)]
     dirty: bool,
 }
 
-impl Settings {
-    pub(crate) fn new() -> Result<Self> {
-        if let Ok(src) = fs::read_to_string(&*SETTINGS_PATH) {
-            Ok(serde_yaml::from_str(&src)?)
-        } else {
-            Ok(Default::default())
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            r#override: None,
+            applied: None,
+            sent_reminders: vec![],
+            flexi_reset: None,
+            launched_apps: vec![],
+            git_include_installed: false,
+            dirty: false,
         }
     }
+}
 
-    pub(crate) fn save(&self) -> Result<()> {
-        if !self.is_dirty() {
-            return Ok(());
+impl Settings {
+    /// Read the config and state from the given files. If the default config file is used and
+    /// does not exist yet, read the legacy settings file which held both instead.
+    pub(crate) fn new(paths: SettingsPaths) -> Result<Self> {
+        if paths.config == *CONFIG_PATH && !paths.config.exists() {
+            if let Some(legacy) = read(&LEGACY_SETTINGS_PATH)? {
+                let (mut config, mut state) = split_legacy(&LEGACY_SETTINGS_PATH, legacy)?;
+                config.dirty = true;
+                state.dirty = true;
+                return Ok(Self {
+                    config,
+                    state,
+                    paths,
+                    migrated_from: Some(LEGACY_SETTINGS_PATH.clone()),
+                    upgraded_from: None,
+                });
+            }
         }
 
-        let settings_dir = SETTINGS_PATH.parent().unwrap();
-        fs::create_dir_all(settings_dir).context("failed to create parent directories")?;
+        let mut moved = Mapping::new();
+        let (mut config, upgraded_from) = match read(&paths.config)? {
+            Some(mut config) => {
+                let version = migration::migrate_config(&paths.config, &mut config, &mut moved)?;
+                (
+                    from_value(&paths.config, config)?,
+                    Some(version).filter(|version| *version < SETTINGS_VERSION),
+                )
+            }
+            None => (Config::default(), None),
+        };
+        config.dirty |= upgraded_from.is_some();
+
+        let mut state = read(&paths.state)?.unwrap_or(Value::Null);
+        let state_version = migration::migrate_state(&paths.state, &mut state)?;
+        // State in the state file is newer than any left in an old config.
+        let mut moved_any = false;
+        if let Some(state) = state.as_mapping_mut() {
+            for (key, value) in moved {
+                if !state.contains_key(&key) {
+                    state.insert(key, value);
+                    moved_any = true;
+                }
+            }
+        }
+        let mut state: State = from_value(&paths.state, state)?;
+        state.dirty |= moved_any || state_version < STATE_VERSION;
+
+        Ok(Self {
+            config,
+            state,
+            paths,
+            migrated_from: None,
+            upgraded_from,
+        })
+    }
+
+    pub(crate) fn save(&mut self) -> Result<()> {
+        if self.config.is_dirty() {
+            if let Some(version) = self.upgraded_from.take() {
+                back_up(&self.paths.config, version)?;
+            }
+            write(&self.paths.config, &self.config)?;
+        }
+        if self.state.dirty {
+            write(&self.paths.state, &self.state)?;
+        }
 
-        let mut settings_file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&*SETTINGS_PATH)
-            .context(format!("failed to write to {}", SETTINGS_PATH.display()))?;
-        Ok(write!(settings_file, "{}", serde_yaml::to_string(self)?)?)
+        if let Some(legacy) = self.migrated_from.as_ref().filter(|path| path.exists()) {
+            let moved = legacy.with_extension("yml.migrated");
+            fs::rename(legacy, &moved)
+                .context(format!("failed to move {} aside", legacy.display()))?;
+            log::info!(
+                config:% = self.paths.config.display(),
+                state:% = self.paths.state.display(),
+                old:% = moved.display();
+                "split settings into config and state"
+            );
+        }
+        Ok(())
     }
 
-    fn is_dirty(&self) -> bool {
-        self.dirty || self.work.dirty() || self.home.dirty() || self.work_hours.dirty()
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub(crate) fn paths(&self) -> &SettingsPaths {
+        &self.paths
     }
 
     pub(crate) fn work(&self) -> &Profile {
-        &self.work
+        &self.config.work
     }
 
     pub(crate) fn work_mut(&mut self) -> &mut Profile {
-        &mut self.work
+        &mut self.config.work
     }
 
     pub(crate) fn home(&self) -> &Profile {
-        &self.home
+        &self.config.home
     }
 
     pub(crate) fn home_mut(&mut self) -> &mut Profile {
-        &mut self.home
+        &mut self.config.home
+    }
+
+    pub(crate) fn profile(&self, profile_type: ProfileType) -> &Profile {
+        match profile_type {
+            ProfileType::Work => self.work(),
+            ProfileType::Home => self.home(),
+        }
     }
 
     pub(crate) fn work_hours(&self) -> &WorkHours {
-        &self.work_hours
+        &self.config.work_hours
     }
 
     pub(crate) fn work_hours_mut(&mut self) -> &mut WorkHours {
-        &mut self.work_hours
+        &mut self.config.work_hours
+    }
+
+    pub(crate) fn desktop(&self) -> Option<DesktopKind> {
+        self.config.desktop
+    }
+
+    pub(crate) fn set_desktop(&mut self, desktop: Option<DesktopKind>) {
+        self.config.dirty = true;
+        self.config.desktop = desktop;
     }
 
     pub(crate) fn r#override(&self) -> Option<&Override> {
-        self.r#override.as_ref()
+        self.state.r#override.as_ref()
     }
 
     pub(crate) fn set_override(&mut self, r#override: Override) {
-        self.dirty = true;
-        self.r#override = Some(r#override);
+        self.state.dirty = true;
+        self.state.r#override = Some(r#override);
     }
 
     pub(crate) fn reset_override(&mut self) {
-        self.dirty = true;
-        self.r#override = None;
+        self.state.dirty = true;
+        self.state.r#override = None;
+    }
+
+    pub(crate) fn applied(&self) -> Option<ProfileType> {
+        self.state.applied
+    }
+
+    pub(crate) fn switch_notification(&self) -> &SwitchNotification {
+        &self.config.switch_notification
+    }
+
+    pub(crate) fn reminders(&self) -> &[Reminder] {
+        &self.config.reminders
+    }
+
+    /// Whether the reminder given the given number of minutes before a switch has been given.
+    pub(crate) fn sent_reminder(&self, before: u64, switch: &DateTime<Local>) -> bool {
+        self.state
+            .sent_reminders
+            .iter()
+            .any(|sent| sent.is_for(before, switch))
+    }
+
+    pub(crate) fn record_sent_reminder(&mut self, sent: SentReminder) {
+        self.state.dirty = true;
+        self.state.sent_reminders.push(sent);
+    }
+
+    /// Forget reminders for switches which have already happened.
+    pub(crate) fn forget_sent_reminders_before(&mut self, time: &DateTime<Local>) {
+        let sent_reminders = &mut self.state.sent_reminders;
+        let count = sent_reminders.len();
+        sent_reminders.retain(|sent| sent.switch() > *time);
+        self.state.dirty |= sent_reminders.len() != count;
+    }
+
+    pub(crate) fn work_time_cap(&self) -> &WorkTimeCap {
+        &self.config.work_time_cap
+    }
+
+    pub(crate) fn leave_days(&self) -> &[NaiveDate] {
+        &self.config.leave_days
+    }
+
+    pub(crate) fn flexi_reset(&self) -> Option<NaiveDate> {
+        self.state.flexi_reset
+    }
+
+    pub(crate) fn set_flexi_reset(&mut self, flexi_reset: NaiveDate) {
+        self.state.dirty = true;
+        self.state.flexi_reset = Some(flexi_reset);
+    }
+
+    pub(crate) fn set_applied(&mut self, applied: ProfileType) {
+        if self.state.applied != Some(applied) {
+            self.state.dirty = true;
+            self.state.applied = Some(applied);
+        }
+    }
+
+    pub(crate) fn launched_apps(&self) -> &[LaunchedApp] {
+        &self.state.launched_apps
+    }
+
+    pub(crate) fn git_include_installed(&self) -> bool {
+        self.state.git_include_installed
+    }
+
+    pub(crate) fn set_git_include_installed(&mut self) {
+        if !self.state.git_include_installed {
+            self.state.dirty = true;
+            self.state.git_include_installed = true;
+        }
+    }
+
+    pub(crate) fn set_launched_apps(&mut self, launched_apps: Vec<LaunchedApp>) {
+        if self.state.launched_apps != launched_apps {
+            self.state.dirty = true;
+            self.state.launched_apps = launched_apps;
+        }
     }
 }
 
-impl Default for Settings {
+#[cfg(test)]
+impl Settings {
+    pub(crate) fn add_leave_day(&mut self, date: NaiveDate) {
+        self.config.leave_days.push(date);
+    }
+
+    pub(crate) fn add_reminder(&mut self, reminder: Reminder) {
+        self.config.reminders.push(reminder);
+    }
+}
+
+impl Config {
+    fn is_dirty(&self) -> bool {
+        self.dirty || self.work.dirty() || self.home.dirty() || self.work_hours.dirty()
+    }
+}
+
+impl Default for Config {
     fn default() -> Self {
         lazy_static! {
             static ref DEFAULT_WORK_START: NaiveTime = NaiveTime::from_hms_opt(6, 0, 0).unwrap();
@@ -110,46 +471,124 @@ impl Default for Settings {
         };
 
         Self {
-            work: Profile {
-                browser: None,
-                background_dir: None,
-                dirty: false,
-                theme: Theme {
-                    gtk: None,
-                    icons: None,
-                    dirty: false,
-                },
-            },
-            home: Profile {
-                browser: None,
-                background_dir: None,
-                dirty: false,
-                theme: Theme {
-                    gtk: None,
-                    icons: None,
-                    dirty: false,
-                },
-            },
+            version: SETTINGS_VERSION,
+            work: Profile::default(),
+            home: Profile::default(),
             work_hours: WorkHours {
                 clock_on: *DEFAULT_WORK_START,
                 clock_off: *DEFAULT_WORK_END,
                 dirty: false,
             },
-            r#override: None,
+            desktop: None,
+            switch_notification: SwitchNotification::default(),
+            reminders: vec![],
+            work_time_cap: WorkTimeCap::default(),
+            leave_days: vec![],
             dirty: false,
         }
     }
 }
 
-#[derive(Debug, Serialise, Deserialise)]
+/// Read a YAML file, if it exists.
+fn read<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    let Ok(src) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    Ok(Some(
+        serde_yaml::from_str(&src).context(format!("failed to read {}", path.display()))?,
+    ))
+}
+
+fn from_value<T: DeserializeOwned>(path: &Path, value: Value) -> Result<T> {
+    Ok(serde_yaml::from_value(value).context(format!("failed to read {}", path.display()))?)
+}
+
+/// Split the legacy settings file, which held the config and state together.
+fn split_legacy(path: &Path, mut legacy: Value) -> Result<(Config, State)> {
+    let mut state = Mapping::new();
+    migration::migrate_config(path, &mut legacy, &mut state)?;
+    let mut state = Value::Mapping(state);
+    migration::migrate_state(path, &mut state)?;
+    Ok((from_value(path, legacy)?, from_value(path, state)?))
+}
+
+/// Copy a file written for an older version of the settings before it is upgraded, numbering the
+/// copy rather than replacing an earlier backup.
+fn back_up(path: &Path, version: u64) -> Result<()> {
+    let backup = (0..)
+        .map(|copy| {
+            let mut name = path.file_name().unwrap_or_default().to_os_string();
+            name.push(format!(".v{version}.bak"));
+            if copy > 0 {
+                name.push(format!(".{copy}"));
+            }
+            path.with_file_name(name)
+        })
+        .find(|backup| !backup.exists())
+        .unwrap_or_default();
+    fs::copy(path, &backup).context(format!("failed to back up {}", path.display()))?;
+    log::info!(path:% = path.display(), backup:% = backup.display(); "backed up settings");
+    Ok(())
+}
+
+fn write(path: &Path, value: &impl Serialise) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("failed to create parent directories")?;
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .context(format!("failed to write to {}", path.display()))?;
+    Ok(write!(file, "{}", serde_yaml::to_string(value)?)?)
+}
+
+#[derive(Debug, Default, Serialise, Deserialise)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct Profile {
     browser: Option<String>,
+    browser_profile: Option<String>,
     background_dir: Option<String>,
 
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    lock_screen: Option<LockScreen>,
+
     #[serde(flatten)]
     theme: Theme,
 
+    #[serde(default)]
+    on_enter: Vec<Hook>,
+
+    #[serde(default)]
+    on_leave: Vec<Hook>,
+
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    apps: Vec<App>,
+
+    /// Seconds to wait for apps to close before killing them.
+    app_close_timeout: Option<u64>,
+
+    /// Desktop IDs of the apps to open each MIME type or URL scheme, such as `mailto:`.
+    #[serde(default)]
+    default_apps: BTreeMap<String, String>,
+
+    #[serde(default)]
+    notifications: Notifications,
+
+    /// Desktop IDs of the apps to pin to the dock, in order.
+    favourite_apps: Option<Vec<String>>,
+
+    #[serde(default)]
+    extensions: Extensions,
+
+    /// The author details to commit with while this profile is active.
+    git: Option<GitIdentity>,
+
+    /// Environment variables for new sessions and shells.
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+
     #[serde(skip)]
     dirty: bool,
 }
@@ -164,6 +603,16 @@ impl Profile {
         self.browser = browser;
     }
 
+    /// The profile within the browser to open, such as a Firefox profile name.
+    pub(crate) fn browser_profile(&self) -> Option<&str> {
+        self.browser_profile.as_deref()
+    }
+
+    pub(crate) fn set_browser_profile(&mut self, browser_profile: Option<String>) {
+        self.dirty = true;
+        self.browser_profile = browser_profile;
+    }
+
     pub(crate) fn background_dir(&self) -> Option<&str> {
         self.background_dir.as_deref()
     }
@@ -173,6 +622,10 @@ impl Profile {
         self.background_dir = background_dir;
     }
 
+    pub(crate) fn lock_screen(&self) -> Option<&LockScreen> {
+        self.lock_screen.as_ref()
+    }
+
     pub(crate) fn theme(&self) -> &Theme {
         &self.theme
     }
@@ -181,6 +634,52 @@ impl Profile {
         &mut self.theme
     }
 
+    /// Commands to run when switching to this profile.
+    pub(crate) fn on_enter(&self) -> &[Hook] {
+        &self.on_enter
+    }
+
+    /// Commands to run when switching away from this profile.
+    pub(crate) fn on_leave(&self) -> &[Hook] {
+        &self.on_leave
+    }
+
+    /// Applications to start when switching to this profile and close when switching away.
+    pub(crate) fn apps(&self) -> &[App] {
+        &self.apps
+    }
+
+    pub(crate) fn default_apps(&self) -> &BTreeMap<String, String> {
+        &self.default_apps
+    }
+
+    pub(crate) fn notifications(&self) -> &Notifications {
+        &self.notifications
+    }
+
+    pub(crate) fn favourite_apps(&self) -> Option<&[String]> {
+        self.favourite_apps.as_deref()
+    }
+
+    pub(crate) fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub(crate) fn git(&self) -> Option<&GitIdentity> {
+        self.git.as_ref()
+    }
+
+    pub(crate) fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    pub(crate) fn app_close_timeout(&self) -> StdDuration {
+        StdDuration::from_secs(
+            self.app_close_timeout
+                .unwrap_or(DEFAULT_APP_CLOSE_TIMEOUT_SECS),
+        )
+    }
+
     pub(crate) fn dirty(&self) -> bool {
         self.dirty || self.theme.dirty()
     }
@@ -194,7 +693,27 @@ pub(crate) enum ProfileType {
     Home,
 }
 
-#[derive(Clone, Debug, Serialise, Deserialise, PartialEq, Eq)]
+impl Display for ProfileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Work => write!(f, "work"),
+            Self::Home => write!(f, "home"),
+        }
+    }
+}
+
+/// Where the lock screen background comes from.
+#[derive(Debug, Serialise, Deserialise)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum LockScreen {
+    /// Use the same image as the desktop background.
+    MirrorBackground,
+
+    /// Pick a random image from a directory, as for the desktop background.
+    Dir(String),
+}
+
+#[derive(Clone, Debug, Default, Serialise, Deserialise, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 #[warn(missing_docs)]
 pub(crate) struct Theme {
@@ -231,6 +750,80 @@ impl Theme {
     }
 }
 
+#[derive(Clone, Debug, Default, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Notifications {
+    show_banners: Option<bool>,
+
+    /// Whether each app, by desktop ID, may send notifications.
+    #[serde(default)]
+    apps: BTreeMap<String, bool>,
+}
+
+impl Notifications {
+    pub(crate) fn show_banners(&self) -> Option<bool> {
+        self.show_banners
+    }
+
+    pub(crate) fn apps(&self) -> &BTreeMap<String, bool> {
+        &self.apps
+    }
+}
+
+/// Shell extensions to switch on and off, by UUID. Extensions not listed are left as they are.
+#[derive(Clone, Debug, Default, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Extensions {
+    #[serde(default)]
+    enable: Vec<String>,
+
+    #[serde(default)]
+    disable: Vec<String>,
+}
+
+impl Extensions {
+    pub(crate) fn enable(&self) -> &[String] {
+        &self.enable
+    }
+
+    pub(crate) fn disable(&self) -> &[String] {
+        &self.disable
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.enable.is_empty() && self.disable.is_empty()
+    }
+}
+
+/// The desktop notification shown when a profile is applied automatically.
+#[derive(Debug, Serialise, Deserialise)]
+#[serde(rename_all = "kebab-case", default)]
+pub(crate) struct SwitchNotification {
+    enabled: bool,
+
+    /// Seconds to wait for an action, such as staying in the previous profile, to be chosen.
+    action_timeout: u64,
+}
+
+impl SwitchNotification {
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn action_timeout(&self) -> StdDuration {
+        StdDuration::from_secs(self.action_timeout)
+    }
+}
+
+impl Default for SwitchNotification {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            action_timeout: DEFAULT_ACTION_TIMEOUT_SECS,
+        }
+    }
+}
+
 #[derive(Debug, Serialise, Deserialise)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct WorkHours {
@@ -272,12 +865,40 @@ pub(crate) struct Override {
 
     #[serde(with = "ts_seconds")]
     date: DateTime<Utc>,
+
+    /// Whether this override was forced past the work-time cap.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    forced: bool,
 }
 
 impl Override {
     pub(crate) fn new(profile_type: ProfileType, duration: Duration) -> Self {
-        let date = (Local::now() + duration).into();
-        Self { date, profile_type }
+        // Whole seconds, as saved, so that the expiry is the same once read back.
+        let date = (Local::now() + duration).trunc_subsecs(0).into();
+        Self {
+            date,
+            profile_type,
+            forced: false,
+        }
+    }
+
+    /// Mark this override as forced past the work-time cap.
+    pub(crate) fn forced(mut self, forced: bool) -> Self {
+        self.forced = forced;
+        self
+    }
+
+    pub(crate) fn is_forced(&self) -> bool {
+        self.forced
+    }
+
+    pub(crate) fn profile_type(&self) -> ProfileType {
+        self.profile_type
+    }
+
+    /// When this override stops being in force.
+    pub(crate) fn until(&self) -> DateTime<Local> {
+        self.date.into()
     }
 
     pub(crate) fn advise_profile(&self) -> Option<ProfileType> {
@@ -288,6 +909,114 @@ impl Override {
     }
 
     fn is_in_force(&self) -> bool {
-        self.date >= Local::now()
+        self.date > Local::now()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn paths() {
+        let from_env = SettingsPaths::resolve(
+            None,
+            Some("/etc/commute/shared.yml".into()),
+            Some("/tmp/commute".into()),
+        );
+        assert_eq!(Path::new("/etc/commute/shared.yml"), from_env.config());
+        assert_eq!(Path::new("/tmp/commute/state.yml"), from_env.state());
+        assert_eq!(Path::new("/tmp/commute/history.jsonl"), from_env.history());
+        assert_eq!(Path::new("/tmp/commute/commute.log"), from_env.log());
+
+        let from_arg = SettingsPaths::resolve(
+            Some("/tmp/commute.yml".into()),
+            Some("/etc/commute/shared.yml".into()),
+            None,
+        );
+        assert_eq!(Path::new("/tmp/commute.yml"), from_arg.config());
+        assert_eq!(SettingsPaths::default().state(), from_arg.state());
+
+        assert_eq!(
+            SettingsPaths::default(),
+            SettingsPaths::resolve(None, None, None)
+        );
+    }
+
+    #[test]
+    fn upgrade() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SettingsPaths {
+            config: dir.path().join("config.yml"),
+            state: dir.path().join("state.yml"),
+            ..Default::default()
+        };
+        let old = "applied: work\nflexi-reset: 2023-08-07\nleave-days: [2023-08-09]\n";
+        fs::write(paths.config(), old).unwrap();
+        fs::write(paths.state(), "applied: home\n").unwrap();
+
+        let mut settings = Settings::new(paths.clone()).unwrap();
+        // The state file is newer than the state left in the config.
+        assert_eq!(Some(ProfileType::Home), settings.applied());
+        assert_eq!(NaiveDate::from_ymd_opt(2023, 8, 7), settings.flexi_reset());
+        settings.save().unwrap();
+
+        let backup = dir.path().join("config.yml.v1.bak");
+        assert_eq!(old, fs::read_to_string(&backup).unwrap());
+        let config = fs::read_to_string(paths.config()).unwrap();
+        assert!(config.starts_with("version: 2\n"));
+        assert!(!config.contains("flexi-reset"));
+        let state = fs::read_to_string(paths.state()).unwrap();
+        assert!(state.starts_with("version: 1\n"));
+        assert!(state.contains("flexi-reset: 2023-08-07"));
+
+        // An earlier backup is kept.
+        fs::write(paths.config(), "leave-days: []\n").unwrap();
+        Settings::new(paths.clone()).unwrap().save().unwrap();
+        assert_eq!(old, fs::read_to_string(&backup).unwrap());
+        assert_eq!(
+            "leave-days: []\n",
+            fs::read_to_string(dir.path().join("config.yml.v1.bak.1")).unwrap()
+        );
+    }
+
+    #[test]
+    fn split_legacy_settings() {
+        let legacy = "\
+work:
+  background-dir: /backgrounds/work
+home: {}
+work-hours:
+  clock-on: 09:00:00
+  clock-off: 17:00:00
+desktop: null
+override:
+  profile-type: home
+  date: 1692000000
+applied: work
+reminders:
+- before: 5
+";
+        let (config, state) = split_legacy(
+            Path::new("settings.yml"),
+            serde_yaml::from_str(legacy).unwrap(),
+        )
+        .unwrap();
+        let settings = Settings {
+            config,
+            state,
+            ..Default::default()
+        };
+        assert_eq!(Some("/backgrounds/work"), settings.work().background_dir());
+        assert_eq!(1, settings.reminders().len());
+        assert_eq!(Some(ProfileType::Work), settings.applied());
+
+        let config = serde_yaml::to_string(&settings.config).unwrap();
+        assert!(config.starts_with("version: 2\n"));
+        assert!(config.contains("work-hours:"));
+        assert!(!config.contains("override:"));
+        let state = serde_yaml::to_string(&settings.state).unwrap();
+        assert!(state.contains("override:"));
+        assert!(!state.contains("work-hours:"));
     }
 }
@@ -0,0 +1,76 @@
+use std::fmt::Display;
+
+use serde::{Deserialize as Deserialise, Serialize as Serialise};
+
+use crate::settings::ProfileType;
+
+/// A change from one profile to another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Transition {
+    from: Option<ProfileType>,
+    to: ProfileType,
+    reason: Reason,
+}
+
+impl Transition {
+    pub(crate) fn new(from: Option<ProfileType>, to: ProfileType, reason: Reason) -> Self {
+        Self { from, to, reason }
+    }
+
+    pub(crate) fn from(&self) -> Option<ProfileType> {
+        self.from
+    }
+
+    pub(crate) fn to(&self) -> ProfileType {
+        self.to
+    }
+
+    pub(crate) fn reason(&self) -> Reason {
+        self.reason
+    }
+
+    /// Whether this transition enters a different profile to the one last applied.
+    pub(crate) fn is_change(&self) -> bool {
+        self.from != Some(self.to)
+    }
+
+    /// Environment variables which describe this transition to user scripts.
+    pub(crate) fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "COMMUTE_OLD_PROFILE",
+                self.from.map(|from| from.to_string()).unwrap_or_default(),
+            ),
+            ("COMMUTE_NEW_PROFILE", self.to.to_string()),
+            ("COMMUTE_REASON", self.reason.to_string()),
+        ]
+    }
+}
+
+/// Why a profile was applied.
+#[derive(Copy, Clone, Debug, Serialise, Deserialise, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Reason {
+    /// The profile was chosen from the work hours.
+    Auto,
+
+    /// The profile was chosen by an earlier override which is still in force.
+    Override,
+
+    /// The profile was requested explicitly.
+    Manual,
+
+    /// The home profile was chosen because the work-time cap was reached.
+    Cap,
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::Override => write!(f, "override"),
+            Self::Manual => write!(f, "manual"),
+            Self::Cap => write!(f, "cap"),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use std::{env, path::PathBuf};
+
+use directories::BaseDirs;
+
+const DEFAULT_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+
+/// The directories to search for data files such as `.desktop` files, most important first.
+pub(crate) fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<_> = BaseDirs::new()
+        .map(|base_dirs| base_dirs.data_dir().to_path_buf())
+        .into_iter()
+        .collect();
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| DEFAULT_DATA_DIRS.into());
+    dirs.extend(env::split_paths(&data_dirs));
+    dirs
+}